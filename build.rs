@@ -12,6 +12,7 @@ const OPCODE_RS_ENUM_PATH: &str = "opcode_rs/enum.rs";
 const OPCODE_RS_BYTE_TO_OPCODE_PATH: &str = "opcode_rs/byte_to_opcode.rs";
 const OPCODE_RS_MNEMONICS_PATH: &str = "opcode_rs/mnemonic_enum.rs";
 const OPCODE_RS_OPCODE_TO_MNEMONICS_PATH: &str = "opcode_rs/opcode_to_mnemonics.rs";
+const OPCODE_RS_CYCLES_PATH: &str = "opcode_rs/cycles.rs";
 
 pub fn main() {
     println!("cargo::rerun-if-changed=build_resources/opcodes.json");
@@ -25,7 +26,8 @@ pub fn main() {
     write_enum_file(&data, &out_dir.join(OPCODE_RS_ENUM_PATH)).unwrap();
     write_byte_to_opcode_file(&data, &out_dir.join(OPCODE_RS_BYTE_TO_OPCODE_PATH)).unwrap();
     write_mnemonic_file(&data, &out_dir.join(OPCODE_RS_MNEMONICS_PATH)).unwrap();
-    write_opcode_to_mnemonic_file(&data, &out_dir.join(OPCODE_RS_OPCODE_TO_MNEMONICS_PATH)).unwrap()
+    write_opcode_to_mnemonic_file(&data, &out_dir.join(OPCODE_RS_OPCODE_TO_MNEMONICS_PATH)).unwrap();
+    write_cycles_file(&data, &out_dir.join(OPCODE_RS_CYCLES_PATH)).unwrap()
 }
 
 // === Data model ===
@@ -42,6 +44,7 @@ struct Operand {
 #[derive(Debug, Clone, Deserialize)]
 struct Instruction {
     mnemonic: String,
+    cycles: Vec<u8>,
     operands: Vec<Operand>,
 }
 
@@ -156,6 +159,29 @@ fn write_opcode_to_mnemonic_file(data: &JsonData, dest: &Path) -> Result<()>{
     Ok(())
 }
 
+fn write_cycles_file(data: &JsonData, dest: &Path) -> Result<()> {
+    let mut f = create_output_file(dest)?;
+
+    writeln!(f, "// Generated by build.rs")?;
+    // Unprefixed, in M-cycles, (taken, not taken) for conditional instructions
+    writeln!(f, "const OPCODE_CYCLES: [(u8, u8); 256] = [")?;
+    for (opcode, inst) in get_opcode_data_sorted(&data["unprefixed"]) {
+        let taken = inst.cycles[0] / 4;
+        let not_taken = inst.cycles.get(1).copied().unwrap_or(inst.cycles[0]) / 4;
+        writeln!(f, "\t/*{opcode:02X}*/ ({taken}, {not_taken}),")?;
+    }
+    writeln!(f, "];\n")?;
+
+    // Prefixed, in M-cycles, including the 0xCB prefix
+    writeln!(f, "const PREFIXED_OPCODE_CYCLES: [u8; 256] = [")?;
+    for (opcode, inst) in get_opcode_data_sorted(&data["cbprefixed"]) {
+        writeln!(f, "\t/*{opcode:02X}*/ {},", inst.cycles[0] / 4)?;
+    }
+    writeln!(f, "];")?;
+
+    Ok(())
+}
+
 // === Helpers ===
 
 fn create_output_file(path: &Path) -> Result<File> {
@@ -1,8 +1,10 @@
-use crate::{cpu::{instructions::Instruction, opcode::{InvalideOpcode, Opcode}, registers::Registers}, utils::open_rom};
+use std::rc::Rc;
 
-pub fn desasm(path: &str) -> Result<(),std::io::Error> {
+use crate::{cpu::{instructions::Instruction, opcode::{InvalideOpcode, Opcode}, registers::Registers}, mem_bus::{cartridge::loader::RomError, mbc::rtc::Clock}, utils::open_rom};
+
+pub fn desasm(path: &str, clock: Rc<dyn Clock>) -> Result<(),RomError> {
         
-        let mem_bus = open_rom(path)?;
+        let mem_bus = open_rom(path, clock)?;
         let mut regs = Registers::zeroed();
         regs.pc = 0x0;
 
@@ -19,7 +21,7 @@ pub fn desasm(path: &str) -> Result<(),std::io::Error> {
         }
 
         let mut instructions = Vec::with_capacity(100);
-        while regs.pc < 0x4000 {
+        while regs.pc < 0x8000 {
             let pc_before = regs.pc;
             let instr_opt = Instruction::try_read(&mut regs, &mem_bus);
             if let Some(instr) = instr_opt {
@@ -41,6 +43,7 @@ pub fn desasm(path: &str) -> Result<(),std::io::Error> {
             }
         }
 
+        println!(";; rom bank 0x{:02X} mapped at 0x4000", mem_bus.cartridge().rom_bank());
         for ligne in instructions.iter() {
             match &ligne.inst_op {
                 InstrOpcode::Instruction(instruction) => {
@@ -1,20 +1,40 @@
-use std::{error::Error, io::Write};
+use std::{error::Error, io::Write, path::Path, rc::Rc};
 
-use crate::{cpu::Cpu, utils::open_rom};
+use crate::{
+    cpu::{Cpu, ErrorPolicy},
+    graphics::{ppu::Renderer, PIX_BLACK, PIX_DARK_GRAY, PIX_LIGHT_GRAY, SCREEN_WIDTH},
+    mem_bus::{
+        cartridge::save::{SaveConfig, SaveError, SaveFile},
+        io::joypad::Button,
+        mbc::rtc::Clock,
+    },
+    utils::open_rom,
+};
 
-const MSG: &str = "[mem/reg/step/break <u16>/clear]: ";
+const MSG: &str = "[mem/reg/step/break <u16>/bt/press <btn>/release <btn>/screen/clear]: ";
 
-pub fn debug(path : &str) -> Result<(), Box<dyn Error>> {
-    let mem_bus = open_rom(path)?;
+pub fn debug(path : &str, save_config: SaveConfig, renderer: Renderer, clock: Rc<dyn Clock>) -> Result<(), Box<dyn Error>> {
+    let mut mem_bus = open_rom(path, clock)?.with_renderer(renderer);
+    if let Some(header) = &mem_bus.cartridge().header {
+        println!(";; cartridge : {}", header.cartridge_type);
+    }
+    let mut save = SaveFile::open(Path::new(path), mem_bus.cartridge_mut(), save_config)?;
+    if let Some(save) = &save {
+        println!(";; save file : {}", save.path().display());
+    }
     let mut cpu = Cpu::new(mem_bus);
+    cpu.on_error = ErrorPolicy::Report;
     let mut break_points: Vec<u16> = vec![];
 
     let stdin = std::io::stdin();
     let mut stdout = std::io::stdout();
     let mut buff = String::new();
-    while !cpu.halted {
+    loop {
         buff.clear();
-        print!("[pc:0x{:04X}]{MSG}", cpu.reg.pc);
+        if let Some(lockup) = &cpu.locked {
+            println!(";; cpu locked up by {lockup}");
+        }
+        print!("[pc:0x{:04X} bank:0x{:02X}]{MSG}", cpu.reg.pc, cpu.mem_bus.cartridge().rom_bank());
         stdout.flush()?;
         stdin.read_line(&mut buff)?;
         let mut split = buff.trim().split_ascii_whitespace();
@@ -26,21 +46,35 @@ pub fn debug(path : &str) -> Result<(), Box<dyn Error>> {
             (Some("m"), _) | (Some("mem"), _) => mem(&cpu),
             (Some("re"), _) | (Some("reg"), _) => reg(&cpu),
             (Some("s"), _) | (Some("step"), _) => step(&mut cpu),
-            (Some("r"), _) | (Some("run"), _) => run(&mut cpu, &break_points),
+            (Some("r"), _) | (Some("run"), _) => run(&mut cpu, &break_points, &mut save)?,
             (Some("b"), Some(arg2)) | (Some("break"), Some(arg2)) => {
                 add_break_point(arg2, &mut break_points)
             }
+            (Some("bt"), _) | (Some("backtrace"), _) => backtrace(&cpu),
+            (Some("press"), Some(arg2)) => set_button(&mut cpu, arg2, true),
+            (Some("release"), Some(arg2)) => set_button(&mut cpu, arg2, false),
+            (Some("sc"), _) | (Some("screen"), _) => screen(&cpu),
             (Some("clear"), _) => print!("\x1B[2J\x1B[1;1H"),
             (Some("exit"), _) => break,
             _ => println!("unknow command : \"{buff}\""),
         }
+
+        if let Some(save) = &mut save {
+            save.tick(cpu.mem_bus.cartridge_mut())?;
+        }
+    }
+
+    if let Some(save) = &mut save {
+        save.flush(cpu.mem_bus.cartridge_mut())?;
     }
 
     Ok(())
 }
 
 fn step(cpu: &mut Cpu) {
-    cpu.step_verbose();
+    if let Err(err) = cpu.step_verbose() {
+        println!(" -- {err} -- ");
+    }
 }
 
 fn reg(cpu: &Cpu) {
@@ -51,11 +85,51 @@ fn mem(cpu: &Cpu) {
     println!("Cpu mem : {:#X?}", cpu.mem_bus)
 }
 
+///Print the calls and interrupts the cpu is in, innermost first
+fn backtrace(cpu: &Cpu) {
+    println!("#0 pc 0x{:04X}", cpu.reg.pc);
+    for (i, frame) in cpu.call_stack.frames().enumerate() {
+        println!("#{} {frame}", i + 1);
+    }
+}
+
+///Print the last frame, one character per pixel
+fn screen(cpu: &Cpu) {
+    let ppu = cpu.mem_bus.ppu();
+    println!(";; ppu mode : {:?}", ppu.mode());
+    for row in ppu.framebuffer().chunks_exact(SCREEN_WIDTH) {
+        let line: String = row
+            .iter()
+            .map(|pix| match *pix {
+                PIX_BLACK => '#',
+                PIX_DARK_GRAY => '+',
+                PIX_LIGHT_GRAY => '.',
+                _ => ' ',
+            })
+            .collect();
+        println!("{line}");
+    }
+}
+
+fn set_button(cpu: &mut Cpu, name: &str, pressed: bool) {
+    let Some(button) = Button::from_name(name) else {
+        println!("unknow button : {name}, expected right/left/up/down/a/b/select/start");
+        return;
+    };
+
+    let joypad = cpu.mem_bus.joypad_mut();
+    if pressed {
+        joypad.press(button);
+    } else {
+        joypad.release(button);
+    }
+}
+
 fn add_break_point(arg2: &str, breaks: &mut Vec<u16>) {
     let addr = if arg2[0..2] == *"0x" {
         u16::from_str_radix(&arg2[2..], 16)
     } else {
-        u16::from_str_radix(arg2, 10)
+        arg2.parse::<u16>()
     };
 
     match addr {
@@ -64,10 +138,22 @@ fn add_break_point(arg2: &str, breaks: &mut Vec<u16>) {
     }
 }
 
-fn run(cpu: &mut Cpu, breaks: &Vec<u16>) {
+fn run(cpu: &mut Cpu, breaks: &[u16], save: &mut Option<SaveFile>) -> Result<(), SaveError> {
     while !breaks.contains(&cpu.reg.pc) {
-        cpu.step();
+        if let Err(err) = cpu.step() {
+            println!(" -- {err} -- ");
+            return Ok(());
+        }
+        // nothing will ever happen again, not even an interrupt
+        if let Some(lockup) = &cpu.locked {
+            println!(" -- Locked up by {lockup} -- ");
+            return Ok(());
+        }
+        if let Some(save) = save {
+            save.tick(cpu.mem_bus.cartridge_mut())?;
+        }
     }
 
-    println!(" -- Break at 0x{:04X} -- ", cpu.reg.pc)
+    println!(" -- Break at 0x{:04X} -- ", cpu.reg.pc);
+    Ok(())
 }
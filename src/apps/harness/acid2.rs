@@ -0,0 +1,116 @@
+use std::{error::Error, path::Path};
+
+use crate::{
+    apps::harness::{CYCLES_PER_SECOND, Outcome, Report, check_reports, print_table, run_guarded},
+    cpu::ErrorPolicy,
+    emulator::Emulator,
+    graphics::{FRAME_SIZE, SCREEN_WIDTH, image::read_png_frame, ppu::Renderer},
+    mem_bus::{MemBus, cartridge::loader::read_rom},
+};
+
+/// The face is drawn after a few frames
+const TIMEOUT: u64 = 10 * CYCLES_PER_SECOND;
+
+/// File names of the dmg-acid2 release
+const ROM_NAME: &str = "dmg-acid2.gb";
+const REFERENCE_NAME: &str = "reference-dmg.png";
+
+///Run dmg-acid2 until it hits the `LD B,B` breakpoint, then compare the next complete frame with the reference
+pub fn run_rom(path: &Path, reference: &[u8; FRAME_SIZE], timeout: u64, renderer: Renderer) -> Report {
+    run_guarded(path, || {
+        let rom = match read_rom(path) {
+            Ok(rom) => rom,
+            Err(err) => return (Outcome::Crashed(err.to_string()), 0),
+        };
+        let mut emulator = Emulator::new(MemBus::from_bytes(&rom).with_renderer(renderer));
+        emulator.cpu.on_error = ErrorPolicy::Stop;
+
+        while emulator.cpu.cycles < timeout {
+            if let Err(err) = emulator.cpu.step() {
+                return (Outcome::Crashed(err.to_string()), emulator.cpu.cycles);
+            }
+            if !emulator.cpu.breakpoint {
+                continue;
+            }
+
+            // the frame being drawn when the breakpoint hits may be partial
+            if let Err(err) = emulator.run_frame() {
+                return (Outcome::Crashed(err.to_string()), emulator.cpu.cycles);
+            }
+            let outcome = match frame_diff(emulator.framebuffer(), reference) {
+                None => Outcome::Passed,
+                Some(diff) => Outcome::Failed(diff),
+            };
+            return (outcome, emulator.cpu.cycles);
+        }
+        (Outcome::Timeout, emulator.cpu.cycles)
+    })
+}
+
+///None if the frames match, else how many pixels differ and where the first one is
+fn frame_diff(frame: &[u8; FRAME_SIZE], reference: &[u8; FRAME_SIZE]) -> Option<String> {
+    let mut diffs = frame.iter().zip(reference).enumerate().filter(|(_, (pix, expected))| pix != expected);
+    let (first, _) = diffs.next()?;
+    Some(format!(
+        "{} pixels differ from the reference, the first one at x = {}, y = {}",
+        diffs.count() + 1,
+        first % SCREEN_WIDTH,
+        first / SCREEN_WIDTH
+    ))
+}
+
+///`gb_emu test-acid2 <dir>`, with dmg-acid2.gb and reference-dmg.png in `dir`
+pub fn test_acid2(dir: &str, renderer: Renderer) -> Result<(), Box<dyn Error>> {
+    let dir = Path::new(dir);
+    let reference = read_png_frame(&std::fs::read(dir.join(REFERENCE_NAME))?)?;
+
+    let report = run_rom(&dir.join(ROM_NAME), &reference, TIMEOUT, renderer);
+    let reports = [report];
+    print_table(dir, &reports);
+    check_reports("dmg-acid2", &reports)
+}
+
+//MARK: TEST
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use crate::{
+        apps::harness::{
+            acid2::{REFERENCE_NAME, ROM_NAME, TIMEOUT, frame_diff, run_rom},
+            check_reports, print_table,
+        },
+        graphics::{FRAME_SIZE, PIX_BLACK, PIX_WHITE, image::read_png_frame, ppu::Renderer},
+    };
+
+    #[test]
+    pub fn test_frame_diff() {
+        let reference = [PIX_WHITE; FRAME_SIZE];
+        let mut frame = reference;
+        assert_eq!(frame_diff(&frame, &reference), None);
+
+        frame[161] = PIX_BLACK;
+        frame[FRAME_SIZE - 1] = PIX_BLACK;
+        assert_eq!(
+            frame_diff(&frame, &reference).unwrap(),
+            "2 pixels differ from the reference, the first one at x = 1, y = 1"
+        );
+    }
+
+    ///Run with `DMG_ACID2=<dir> cargo test -- --ignored`, checks both renderers
+    #[test]
+    #[ignore = "needs dmg-acid2.gb and reference-dmg.png in DMG_ACID2"]
+    pub fn test_dmg_acid2() {
+        let dir = std::env::var("DMG_ACID2").expect("DMG_ACID2 is not set");
+        let dir = Path::new(&dir);
+        let reference = std::fs::read(dir.join(REFERENCE_NAME)).expect("could not read the reference image");
+        let reference = read_png_frame(&reference).unwrap();
+
+        for renderer in [Renderer::Scanline, Renderer::Fifo] {
+            let reports = [run_rom(&dir.join(ROM_NAME), &reference, TIMEOUT, renderer)];
+            print_table(dir, &reports);
+            check_reports(&format!("dmg-acid2 {renderer:?}"), &reports).unwrap();
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use std::{error::Error, path::Path};
+
+use crate::{
+    apps::harness::{CYCLES_PER_SECOND, Outcome, Report, check_reports, run_dir, run_guarded},
+    cpu::ErrorPolicy,
+    emulator::Emulator,
+    mem_bus::{MemBus, cartridge::loader::read_rom, io::serial::Capture},
+};
+
+/// The longest blargg roms, like cpu_instrs as a whole, need about a minute
+const TIMEOUT: u64 = 120 * CYCLES_PER_SECOND;
+
+///Run a blargg rom until it prints "Passed" or "Failed" over the serial port
+pub fn run_rom(path: &Path, timeout: u64) -> Report {
+    run_guarded(path, || {
+        let rom = match read_rom(path) {
+            Ok(rom) => rom,
+            Err(err) => return (Outcome::Crashed(err.to_string()), 0),
+        };
+
+        let mut mem_bus = MemBus::from_bytes(&rom);
+        let capture = Capture::default();
+        let serial = capture.output();
+        mem_bus.set_serial_link(Box::new(capture));
+        let mut emulator = Emulator::new(mem_bus);
+        emulator.cpu.on_error = ErrorPolicy::Stop;
+
+        // bytes of the output already searched
+        let mut scanned = 0;
+        while emulator.cpu.cycles < timeout {
+            if let Err(err) = emulator.run_frame() {
+                return (Outcome::Crashed(err.to_string()), emulator.cpu.cycles);
+            }
+
+            let output = serial.borrow();
+            if appended_contains(&output, scanned, b"Passed") {
+                return (Outcome::Passed, emulator.cpu.cycles);
+            }
+            if appended_contains(&output, scanned, b"Failed") {
+                return (Outcome::Failed(String::from_utf8_lossy(&output).into_owned()), emulator.cpu.cycles);
+            }
+            scanned = output.len();
+        }
+        (Outcome::Timeout, emulator.cpu.cycles)
+    })
+}
+
+///Whether `needle` shows up in `output`, only looking at what was appended after `scanned`
+fn appended_contains(output: &[u8], scanned: usize, needle: &[u8]) -> bool {
+    let start = scanned.saturating_sub(needle.len() - 1);
+    output[start..].windows(needle.len()).any(|window| window == needle)
+}
+
+///`gb_emu test-blargg <dir>`, fails if any rom did not pass
+pub fn test_blargg(dir: &str) -> Result<(), Box<dyn Error>> {
+    let reports = run_dir(Path::new(dir), |rom| run_rom(rom, TIMEOUT))?;
+    check_reports("blargg", &reports)
+}
+
+//MARK: TEST
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use crate::apps::harness::{
+        blargg::{TIMEOUT, appended_contains, run_rom},
+        check_reports, run_dir,
+    };
+
+    #[test]
+    pub fn test_appended_contains() {
+        let output = b"cpu_instrs\n\nPassed";
+        assert!(appended_contains(output, 0, b"Passed"));
+        // the word straddles what was already scanned
+        assert!(appended_contains(output, output.len() - 2, b"Passed"));
+        assert!(!appended_contains(output, output.len(), b"Passed"));
+        assert!(!appended_contains(output, 0, b"Failed"));
+    }
+
+    ///Run with `BLARGG_ROMS=<dir> cargo test -- --ignored`
+    #[test]
+    #[ignore = "needs the blargg test roms in BLARGG_ROMS"]
+    pub fn test_blargg_roms() {
+        let dir = std::env::var("BLARGG_ROMS").expect("BLARGG_ROMS is not set");
+
+        let reports = run_dir(Path::new(&dir), |rom| run_rom(rom, TIMEOUT)).expect("could not read BLARGG_ROMS");
+        check_reports("blargg", &reports).unwrap();
+    }
+}
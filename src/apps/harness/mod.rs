@@ -0,0 +1,115 @@
+use std::{
+    error::Error,
+    fmt::Display,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+};
+
+pub mod acid2;
+pub mod blargg;
+pub mod mooneye;
+
+/// M-cycles in one emulated second
+pub const CYCLES_PER_SECOND: u64 = 1 << 20;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    Passed,
+    ///With what the rom reported
+    Failed(String),
+    Timeout,
+    ///The rom could not be loaded or the emulator panicked
+    Crashed(String),
+}
+
+impl Outcome {
+    pub fn is_passed(&self) -> bool {
+        *self == Outcome::Passed
+    }
+}
+
+impl Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Outcome::Passed => write!(f, "passed"),
+            Outcome::Failed(_) => write!(f, "FAILED"),
+            Outcome::Timeout => write!(f, "TIMEOUT"),
+            Outcome::Crashed(_) => write!(f, "CRASHED"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Report {
+    pub rom: PathBuf,
+    pub outcome: Outcome,
+    /// M-cycles run before the outcome was known
+    pub cycles: u64,
+}
+
+///Run a test rom, turning a panic of the emulator into `Outcome::Crashed`
+pub fn run_guarded(rom: &Path, run: impl FnOnce() -> (Outcome, u64)) -> Report {
+    let (outcome, cycles) = panic::catch_unwind(AssertUnwindSafe(run)).unwrap_or_else(|err| {
+        let msg = err
+            .downcast_ref::<String>()
+            .cloned()
+            .or_else(|| err.downcast_ref::<&str>().map(|s| s.to_string()))
+            .unwrap_or_default();
+        (Outcome::Crashed(msg), 0)
+    });
+    Report { rom: rom.to_path_buf(), outcome, cycles }
+}
+
+///Every .gb file under `dir`, sorted
+pub fn find_roms(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut roms = vec![];
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("gb")) {
+                roms.push(path);
+            }
+        }
+    }
+    roms.sort();
+    Ok(roms)
+}
+
+///Run every rom of a directory and print a results table
+pub fn run_dir(dir: &Path, run_rom: impl Fn(&Path) -> Report) -> std::io::Result<Vec<Report>> {
+    let reports: Vec<Report> = find_roms(dir)?.iter().map(|rom| run_rom(rom)).collect();
+    print_table(dir, &reports);
+    Ok(reports)
+}
+
+///Error out if any rom did not pass, for the exit code of the test commands
+pub fn check_reports(suite: &str, reports: &[Report]) -> Result<(), Box<dyn Error>> {
+    let failed = reports.iter().filter(|report| !report.outcome.is_passed()).count();
+    if failed > 0 {
+        Err(format!("{failed} {suite} rom(s) did not pass"))?;
+    }
+    Ok(())
+}
+
+///One line per rom, then the details of the failures
+pub fn print_table(dir: &Path, reports: &[Report]) {
+    let name = |report: &Report| report.rom.strip_prefix(dir).unwrap_or(&report.rom).display().to_string();
+    let width = reports.iter().map(|report| name(report).len()).max().unwrap_or(0).max(3);
+
+    println!("{:<width$}  {:<8}  {:>12}", "ROM", "RESULT", "M-CYCLES");
+    for report in reports {
+        println!("{:<width$}  {:<8}  {:>12}", name(report), report.outcome.to_string(), report.cycles);
+    }
+
+    for report in reports {
+        if let Outcome::Failed(details) | Outcome::Crashed(details) = &report.outcome {
+            println!("\n;; {} :\n{}", name(report), details.trim_end());
+        }
+    }
+
+    let passed = reports.iter().filter(|report| report.outcome.is_passed()).count();
+    println!("\n{passed}/{} passed", reports.len());
+}
@@ -0,0 +1,83 @@
+use std::{error::Error, path::Path};
+
+use crate::{
+    apps::harness::{CYCLES_PER_SECOND, Outcome, Report, check_reports, run_dir, run_guarded},
+    cpu::{ErrorPolicy, registers::Registers},
+    emulator::Emulator,
+    graphics::ppu::Renderer,
+    mem_bus::{MemBus, cartridge::loader::read_rom},
+};
+
+/// Mooneye roms report within a few emulated seconds
+const TIMEOUT: u64 = 20 * CYCLES_PER_SECOND;
+
+/// B, C, D, E, H and L when a test passes
+const PASS_SIGNATURE: [u8; 6] = [3, 5, 8, 13, 21, 34];
+
+const fn signature(reg: &Registers) -> [u8; 6] {
+    [reg.b, reg.c, reg.d, reg.e, reg.h, reg.l]
+}
+
+///Run a mooneye rom until it hits the `LD B,B` breakpoint, then check the registers
+pub fn run_rom(path: &Path, timeout: u64, renderer: Renderer) -> Report {
+    run_guarded(path, || {
+        let rom = match read_rom(path) {
+            Ok(rom) => rom,
+            Err(err) => return (Outcome::Crashed(err.to_string()), 0),
+        };
+        let mut emulator = Emulator::new(MemBus::from_bytes(&rom).with_renderer(renderer));
+        emulator.cpu.on_error = ErrorPolicy::Stop;
+
+        while emulator.cpu.cycles < timeout {
+            if let Err(err) = emulator.cpu.step() {
+                return (Outcome::Crashed(err.to_string()), emulator.cpu.cycles);
+            }
+            if !emulator.cpu.breakpoint {
+                continue;
+            }
+
+            // failures set all of them to 0x42
+            let outcome = match signature(&emulator.cpu.reg) {
+                PASS_SIGNATURE => Outcome::Passed,
+                regs => Outcome::Failed(format!("b c d e h l = {regs:02X?}")),
+            };
+            return (outcome, emulator.cpu.cycles);
+        }
+        (Outcome::Timeout, emulator.cpu.cycles)
+    })
+}
+
+///`gb_emu test-mooneye <dir>`, fails if any rom did not pass
+pub fn test_mooneye(dir: &str, renderer: Renderer) -> Result<(), Box<dyn Error>> {
+    let reports = run_dir(Path::new(dir), |rom| run_rom(rom, TIMEOUT, renderer))?;
+    check_reports("mooneye", &reports)
+}
+
+//MARK: TEST
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use crate::{
+        apps::harness::{
+            check_reports,
+            mooneye::{TIMEOUT, run_rom},
+            run_dir,
+        },
+        graphics::ppu::Renderer,
+    };
+
+    ///Run with `MOONEYE_ROMS=<dir> cargo test -- --ignored`, checks both renderers
+    #[test]
+    #[ignore = "needs the mooneye test roms in MOONEYE_ROMS"]
+    pub fn test_mooneye_roms() {
+        let dir = std::env::var("MOONEYE_ROMS").expect("MOONEYE_ROMS is not set");
+
+        for renderer in [Renderer::Scanline, Renderer::Fifo] {
+            let reports =
+                run_dir(Path::new(&dir), |rom| run_rom(rom, TIMEOUT, renderer)).expect("could not read MOONEYE_ROMS");
+            check_reports(&format!("mooneye {renderer:?}"), &reports).unwrap();
+        }
+    }
+}
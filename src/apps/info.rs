@@ -0,0 +1,12 @@
+use std::error::Error;
+
+use crate::mem_bus::cartridge::CartridgeHeader;
+
+pub fn info(path: &str) -> Result<(), Box<dyn Error>> {
+    let rom = std::fs::read(path)?;
+    let header = CartridgeHeader::parse(&rom)?;
+
+    println!("{header}");
+
+    Ok(())
+}
@@ -1,2 +1,6 @@
 pub mod debugger;
 pub mod deasm;
+pub mod harness;
+pub mod info;
+pub mod screenshot;
+pub mod terminal;
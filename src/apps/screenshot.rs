@@ -0,0 +1,73 @@
+use std::{
+    error::Error,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    rc::Rc,
+};
+
+use crate::{
+    emulator::Emulator,
+    graphics::{
+        image::{ImageFormat, Palette},
+        ppu::Renderer,
+    },
+    mem_bus::{io::serial::Capture, mbc::rtc::Clock},
+    utils::open_rom,
+};
+
+///What is plugged in the link port while taking a screenshot
+#[derive(Debug, Clone, Copy)]
+pub enum LinkPort<'a> {
+    ///Record the bytes sent, and print them after the screenshot if asked to
+    Capture { print: bool },
+    ///A second rom runs alongside, one frame each in turn
+    Rom(&'a Path),
+}
+
+///Run a rom headless for some frames and save the last one as png or ppm, depending on the extension
+pub fn screenshot(
+    path: &str,
+    frames: u32,
+    out: &Path,
+    palette: &Palette,
+    renderer: Renderer,
+    link: LinkPort,
+    clock: Rc<dyn Clock>,
+) -> Result<(), Box<dyn Error>> {
+    let format = ImageFormat::from_path(out)
+        .ok_or_else(|| format!("Unsuported image format : {}, use .png or .ppm", out.display()))?;
+
+    let mut mem_bus = open_rom(path, clock.clone())?.with_renderer(renderer);
+    let capture = Capture::default();
+    let serial_output = capture.output();
+    mem_bus.set_serial_link(Box::new(capture));
+
+    let mut emulator = Emulator::new(mem_bus);
+    let mut linked = match link {
+        LinkPort::Rom(link) => {
+            let mut other = Emulator::new(open_rom(&link.to_string_lossy(), clock)?.with_renderer(renderer));
+            emulator.link(&mut other);
+            Some(other)
+        }
+        LinkPort::Capture { .. } => None,
+    };
+
+    for _ in 0..frames {
+        emulator.run_frame()?;
+        if let Some(other) = &mut linked {
+            other.run_frame()?;
+        }
+    }
+
+    let mut writer = BufWriter::new(File::create(out)?);
+    format.write(&mut writer, emulator.framebuffer(), palette)?;
+    // dropping the writer would silently ignore an error on the last write
+    writer.flush()?;
+    println!(";; frame {frames} written to {}", out.display());
+    if let LinkPort::Capture { print: true } = link {
+        println!(";; serial output :\n{}", String::from_utf8_lossy(&serial_output.borrow()));
+    }
+
+    Ok(())
+}
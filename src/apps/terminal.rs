@@ -0,0 +1,273 @@
+use std::{
+    error::Error,
+    fmt::Write as _,
+    io::{Read, Write},
+    path::Path,
+    process::{Command, Stdio},
+    rc::Rc,
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    emulator::Emulator,
+    graphics::{FRAME_SIZE, SCREEN_HEIGHT, SCREEN_WIDTH, image::Palette, ppu::Renderer},
+    mem_bus::{
+        cartridge::save::{SaveConfig, SaveFile},
+        io::joypad::Button,
+        mbc::rtc::Clock,
+    },
+    utils::open_rom,
+};
+
+/// One frame of the DMG, refreshed at ~59.73 Hz
+const FRAME_DURATION: Duration = Duration::from_nanos(16_742_706);
+/// Terminals do not report key releases, a button stays pressed this many frames after the last key repeat
+const HOLD_FRAMES: u8 = 8;
+
+const KEYS_MSG: &str = "arrows/wasd : dpad, x : A, z : B, enter : start, space : select, q : quit";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Input {
+    Button(Button),
+    Quit,
+}
+
+///Put the terminal in raw mode, restores it when dropped, even on panic
+struct RawMode {
+    saved: String,
+}
+
+impl RawMode {
+    fn enable() -> Result<Self, Box<dyn Error>> {
+        let output = Command::new("stty").arg("-g").stdin(Stdio::inherit()).output()?;
+        if !output.status.success() {
+            Err("the terminal frontend needs stdin to be a terminal")?;
+        }
+        let saved = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        Command::new("stty").args(["raw", "-echo"]).status()?;
+
+        // hide the cursor and clear the screen
+        print!("\x1B[?25l\x1B[2J");
+        Ok(Self { saved })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = Command::new("stty").arg(&self.saved).status();
+        print!("\x1B[0m\x1B[?25h\r\n");
+        let _ = std::io::stdout().flush();
+    }
+}
+
+///Play a rom in the terminal, two pixels per character with `▀` half blocks
+pub fn play(
+    path: &str,
+    save_config: SaveConfig,
+    palette: &Palette,
+    renderer: Renderer,
+    clock: Rc<dyn Clock>,
+) -> Result<(), Box<dyn Error>> {
+    let mut mem_bus = open_rom(path, clock)?.with_renderer(renderer);
+    let mut save = SaveFile::open(Path::new(path), mem_bus.cartridge_mut(), save_config)?;
+    let mut emulator = Emulator::new(mem_bus);
+
+    let _raw_mode = RawMode::enable()?;
+    let keys = spawn_input_thread();
+    let mut held = [0u8; Button::ALL.len()];
+    let mut stdout = std::io::stdout();
+    let mut next_frame = Instant::now();
+    let mut pending = vec![];
+
+    'frames: loop {
+        pending.extend(keys.try_iter());
+        for input in parse_inputs(&mut pending) {
+            match input {
+                Input::Quit => break 'frames,
+                Input::Button(button) => {
+                    held[button as usize] = HOLD_FRAMES;
+                    emulator.press(button);
+                }
+            }
+        }
+
+        emulator.run_frame()?;
+
+        for button in Button::ALL {
+            let frames = &mut held[button as usize];
+            if *frames > 0 {
+                *frames -= 1;
+                if *frames == 0 {
+                    emulator.release(button);
+                }
+            }
+        }
+
+        stdout.write_all(render(emulator.framebuffer(), palette).as_bytes())?;
+        stdout.flush()?;
+
+        if let Some(save) = &mut save {
+            save.tick(emulator.cpu.mem_bus.cartridge_mut())?;
+        }
+
+        next_frame += FRAME_DURATION;
+        let now = Instant::now();
+        if next_frame > now {
+            thread::sleep(next_frame - now);
+        } else {
+            // too slow to keep up, do not try to catch up
+            next_frame = now;
+        }
+    }
+
+    if let Some(save) = &mut save {
+        save.flush(emulator.cpu.mem_bus.cartridge_mut())?;
+    }
+
+    Ok(())
+}
+
+///Blocking stdin reads happen on their own thread, so the frame loop only polls
+fn spawn_input_thread() -> Receiver<u8> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for byte in std::io::stdin().lock().bytes() {
+            let Ok(byte) = byte else { break };
+            if sender.send(byte).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
+
+///Turn the bytes read so far into inputs, an escape sequence cut between two reads stays in `pending`
+fn parse_inputs(pending: &mut Vec<u8>) -> Vec<Input> {
+    let mut inputs = vec![];
+    let mut rest = &pending[..];
+
+    loop {
+        let (input, len) = match rest {
+            [] => break,
+            // CSI: parameter and intermediate bytes up to a final byte in 0x40 -> 0x7E
+            [0x1B, b'[', sequence @ ..] => {
+                let Some(end) = sequence.iter().position(|byte| (0x40..=0x7E).contains(byte)) else {
+                    break;
+                };
+                let button = match &sequence[..=end] {
+                    b"A" => Some(Button::Up),
+                    b"B" => Some(Button::Down),
+                    b"C" => Some(Button::Right),
+                    b"D" => Some(Button::Left),
+                    _ => None,
+                };
+                (button.map(Input::Button), 2 + end + 1)
+            }
+            [0x1B] => break,
+            // alt + key, ignored instead of read as a plain key
+            [0x1B, _, ..] => (None, 2),
+            [byte, ..] => {
+                let input = match byte.to_ascii_lowercase() {
+                    b'w' => Some(Input::Button(Button::Up)),
+                    b's' => Some(Input::Button(Button::Down)),
+                    b'd' => Some(Input::Button(Button::Right)),
+                    b'a' => Some(Input::Button(Button::Left)),
+                    b'x' => Some(Input::Button(Button::A)),
+                    b'z' => Some(Input::Button(Button::B)),
+                    b'\r' | b'\n' => Some(Input::Button(Button::Start)),
+                    b' ' => Some(Input::Button(Button::Select)),
+                    b'q' | 0x03 => Some(Input::Quit), // ctrl-c is not a signal in raw mode
+                    _ => None,
+                };
+                (input, 1)
+            }
+        };
+        inputs.extend(input);
+        rest = &rest[len..];
+    }
+
+    let parsed = pending.len() - rest.len();
+    pending.drain(..parsed);
+    inputs
+}
+
+///Escape sequences drawing the frame from the top left corner, colors are only sent when they change
+fn render(frame: &[u8; FRAME_SIZE], palette: &Palette) -> String {
+    let mut out = String::with_capacity(FRAME_SIZE * 8);
+    out.push_str("\x1B[H");
+
+    for y in (0..SCREEN_HEIGHT).step_by(2) {
+        let (mut last_top, mut last_bottom) = (None, None);
+        for x in 0..SCREEN_WIDTH {
+            let top = palette.color(frame[y * SCREEN_WIDTH + x]);
+            let bottom = palette.color(frame[(y + 1) * SCREEN_WIDTH + x]);
+            if last_top != Some(top) {
+                let _ = write!(out, "\x1B[38;2;{};{};{}m", top[0], top[1], top[2]);
+                last_top = Some(top);
+            }
+            if last_bottom != Some(bottom) {
+                let _ = write!(out, "\x1B[48;2;{};{};{}m", bottom[0], bottom[1], bottom[2]);
+                last_bottom = Some(bottom);
+            }
+            out.push('▀');
+        }
+        out.push_str("\x1B[0m\r\n");
+    }
+    out.push_str(KEYS_MSG);
+
+    out
+}
+
+//MARK: TEST
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        apps::terminal::{Input, parse_inputs, render},
+        graphics::{FRAME_SIZE, PIX_BLACK, PIX_WHITE, SCREEN_HEIGHT, image::Palette},
+        mem_bus::io::joypad::Button,
+    };
+
+    #[test]
+    pub fn test_parse_inputs() {
+        let mut pending = b"\x1B[Ax\x1B[Dq?".to_vec();
+        assert_eq!(
+            parse_inputs(&mut pending),
+            vec![Input::Button(Button::Up), Input::Button(Button::A), Input::Button(Button::Left), Input::Quit]
+        );
+        assert!(pending.is_empty());
+
+        // ctrl + left, and alt + d, are not plain keys
+        let mut pending = b"\x1B[1;5D\x1BDz".to_vec();
+        assert_eq!(parse_inputs(&mut pending), vec![Input::Button(Button::B)]);
+    }
+
+    #[test]
+    pub fn test_split_escape_sequence() {
+        let mut pending = b"x\x1B[".to_vec();
+        assert_eq!(parse_inputs(&mut pending), vec![Input::Button(Button::A)]);
+        assert_eq!(pending, b"\x1B[");
+
+        pending.push(b'A');
+        assert_eq!(parse_inputs(&mut pending), vec![Input::Button(Button::Up)]);
+        assert!(pending.is_empty());
+
+        let mut pending = b"\x1B".to_vec();
+        assert_eq!(parse_inputs(&mut pending), vec![]);
+        pending.extend_from_slice(b"[D");
+        assert_eq!(parse_inputs(&mut pending), vec![Input::Button(Button::Left)]);
+    }
+
+    #[test]
+    pub fn test_render_half_blocks() {
+        let mut frame = [PIX_WHITE; FRAME_SIZE];
+        frame[160] = PIX_BLACK;
+        let out = render(&frame, &Palette::default());
+
+        assert!(out.starts_with("\x1B[H\x1B[38;2;255;255;255m\x1B[48;2;0;0;0m▀\x1B[48;2;255;255;255m▀▀"));
+        assert_eq!(out.matches('▀').count(), FRAME_SIZE / 2);
+        assert_eq!(out.matches("\r\n").count(), SCREEN_HEIGHT / 2);
+    }
+}
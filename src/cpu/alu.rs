@@ -6,10 +6,11 @@ use crate::{
             ArithmeticInstruction, ArithmeticTarget, Immediate, Immediate3Bits, Instruction,
         },
     },
+    mem_bus::Bus,
     utils::Value,
 };
 
-impl Cpu {
+impl<B: Bus> Cpu<B> {
     pub(super) fn alu(
         &mut self,
         instruction: ArithmeticInstruction,
@@ -537,3 +538,67 @@ impl Cpu {
         self.reg.set_carry(true);
     }
 }
+
+//MARK: TEST
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        cpu::{Cpu, ErrorPolicy},
+        mem_bus::MemBus,
+    };
+
+    ///Run one `ADD HL,rr` with HL and the other pair already set, Z set beforehand
+    fn add_hl(opcode: u8, setup: impl FnOnce(&mut Cpu)) -> Cpu {
+        let mut cpu = Cpu::new(MemBus::from_bytes(&[opcode]));
+        cpu.on_error = ErrorPolicy::Report;
+        cpu.reg.set_zero(true);
+        cpu.reg.set_substract(true);
+        setup(&mut cpu);
+        cpu.step().unwrap();
+        assert!(cpu.reg.get_zero(), "ADD HL must not touch Z");
+        assert!(!cpu.reg.get_substract());
+        cpu
+    }
+
+    #[test]
+    pub fn test_add_hl_bc() {
+        let cpu = add_hl(0x09, |cpu| {
+            cpu.reg.set_hl(0x0FFF);
+            cpu.reg.set_bc(0x0001);
+        });
+        assert_eq!(cpu.reg.get_hl(), 0x1000);
+        assert!(cpu.reg.get_half_carry());
+        assert!(!cpu.reg.get_carry());
+    }
+
+    #[test]
+    pub fn test_add_hl_de() {
+        let cpu = add_hl(0x19, |cpu| {
+            cpu.reg.set_hl(0x8000);
+            cpu.reg.set_de(0x8000);
+        });
+        assert_eq!(cpu.reg.get_hl(), 0x0000);
+        assert!(!cpu.reg.get_half_carry());
+        assert!(cpu.reg.get_carry());
+    }
+
+    #[test]
+    pub fn test_add_hl_hl() {
+        let cpu = add_hl(0x29, |cpu| cpu.reg.set_hl(0x8800));
+        assert_eq!(cpu.reg.get_hl(), 0x1000);
+        assert!(cpu.reg.get_half_carry());
+        assert!(cpu.reg.get_carry());
+    }
+
+    #[test]
+    pub fn test_add_hl_sp() {
+        let cpu = add_hl(0x39, |cpu| {
+            cpu.reg.set_hl(0x1234);
+            cpu.reg.sp = 0x0100;
+        });
+        assert_eq!(cpu.reg.get_hl(), 0x1334);
+        assert!(!cpu.reg.get_half_carry());
+        assert!(!cpu.reg.get_carry());
+    }
+}
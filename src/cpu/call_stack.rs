@@ -0,0 +1,89 @@
+use std::fmt::Display;
+
+use crate::cpu::interrupts::Interrupt;
+
+///What pushed a return address
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrameKind {
+    Call,
+    Rst,
+    Interrupt(Interrupt),
+}
+
+///A return address the cpu pushed, mirrored outside of the emulated memory
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frame {
+    pub kind: FrameKind,
+    ///Where the call or the interrupt went
+    pub target: u16,
+    pub return_addr: u16,
+    ///SP right after the push, the return address lives there
+    pub sp: u16,
+}
+
+impl Display for Frame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            FrameKind::Call => write!(f, "call 0x{:04X}", self.target)?,
+            FrameKind::Rst => write!(f, "rst 0x{:02X}", self.target)?,
+            FrameKind::Interrupt(interrupt) => write!(f, "{interrupt:?} interrupt 0x{:04X}", self.target)?,
+        }
+        write!(f, ", returns to 0x{:04X} [sp 0x{:04X}]", self.return_addr, self.sp)
+    }
+}
+
+///Shadow of the calls the cpu is in, for backtraces.
+///Games are free to drop or forge return addresses, so frames are matched by their SP
+#[derive(Debug, Default)]
+pub struct CallStack {
+    frames: Vec<Frame>,
+}
+
+impl CallStack {
+    pub fn push(&mut self, frame: Frame) {
+        // anything at or below the new frame has been overwritten
+        self.frames.retain(|f| f.sp > frame.sp);
+        self.frames.push(frame);
+    }
+
+    ///Forget the frames a return from `sp` unwinds
+    pub fn pop(&mut self, sp: u16) {
+        self.frames.retain(|f| f.sp > sp);
+    }
+
+    ///Innermost frame first
+    pub fn frames(&self) -> impl Iterator<Item = &Frame> {
+        self.frames.iter().rev()
+    }
+}
+
+//MARK: TEST
+
+#[cfg(test)]
+mod test {
+    use crate::cpu::{
+        call_stack::{CallStack, Frame, FrameKind},
+        interrupts::Interrupt,
+    };
+
+    fn frame(kind: FrameKind, sp: u16) -> Frame {
+        Frame { kind, target: 0x0040, return_addr: 0x0150, sp }
+    }
+
+    #[test]
+    pub fn test_frames_follow_sp() {
+        let mut stack = CallStack::default();
+        stack.push(frame(FrameKind::Call, 0xCFFE));
+        stack.push(frame(FrameKind::Interrupt(Interrupt::VBlank), 0xCFFC));
+        stack.push(frame(FrameKind::Rst, 0xCFFA));
+
+        // a return from further up the stack unwinds everything below it
+        stack.pop(0xCFFC);
+        assert_eq!(stack.frames().collect::<Vec<_>>(), [&frame(FrameKind::Call, 0xCFFE)]);
+
+        // the stack pointer was moved back up without returning
+        stack.push(frame(FrameKind::Rst, 0xCFFC));
+        stack.push(frame(FrameKind::Call, 0xCFFE));
+        assert_eq!(stack.frames().collect::<Vec<_>>(), [&frame(FrameKind::Call, 0xCFFE)]);
+    }
+}
@@ -2,16 +2,27 @@ use crate::{
     cpu::{
         instructions::{ArithmeticInstruction, ArithmeticTarget, ByteLoadDest, Immediate, Instruction, JumpInstruction, JumpTarget, JumpTest, LoadDest, LoadSrc, MiscInstruction, StackInstruction, StackReg16, WordLoadDest}, opcode::{Mnemonic, Opcode}, registers::Registers
     },
-    mem_bus::MemBus,
+    mem_bus::Bus,
 };
 
 impl Instruction {
     ///Read the instruction point by pc, and increment
-    pub fn try_read(reg : &mut Registers, mem_bus: &MemBus) -> Option<Instruction> {
+    pub fn try_read<B: Bus>(reg : &mut Registers, mem_bus: &B) -> Option<Instruction> {
         let byte = mem_bus.readb(reg.pc);
         reg.pc = reg.pc.wrapping_add(1);
 
+        Self::decode(byte, reg, mem_bus)
+    }
+
+    ///Read the instruction point by pc, without incrementing pc after the opcode (HALT bug)
+    pub fn try_read_halt_bug<B: Bus>(reg : &mut Registers, mem_bus: &B) -> Option<Instruction> {
+        let byte = mem_bus.readb(reg.pc);
+
+        Self::decode(byte, reg, mem_bus)
+    }
 
+    ///Decode the instruction of opcode byte, reading its operands from pc
+    fn decode<B: Bus>(byte: u8, reg : &mut Registers, mem_bus: &B) -> Option<Instruction> {
         if let Ok(opcode) = Opcode::try_from(byte) {
             Some(match opcode.get_mnemonic() {
                 // MARK: ALU INSTRUCTIONS
@@ -20,7 +31,8 @@ impl Instruction {
 	            Mnemonic::Add => {
                     //16 bits case
                     if byte & 0b1100_1111 == 0b0000_1001{
-                        Instruction::Arithmetic(ArithmeticInstruction::Add, None, Some(byte_to_16_arithmetic_target(byte)))
+                        let operand = byte >> 4;
+                        Instruction::Arithmetic(ArithmeticInstruction::AddHl, None, Some(byte_to_16_arithmetic_target(operand)))
                     }
                     //8bits case
                     else if opcode == Opcode::AddAN8{
@@ -154,7 +166,7 @@ impl Instruction {
                 }
                 //Rst
                 Mnemonic::Rst => {
-                    let target = byte & 0b0001_1000;
+                    let target = byte & 0b0011_1000;
                     Instruction::Jump(JumpInstruction::Rst, JumpTest::Always, Some(JumpTarget::Imm16(target as u16)))
                 }
                 // MARK: LOAD INSTRUCTIONS
@@ -281,14 +293,14 @@ impl Instruction {
                 | Mnemonic::IllegalFc
                 | Mnemonic::IllegalFd => return None,
                 
-                _ => unimplemented!("{opcode:?}"),
+                _ => return None,
             })
         } else {
             None
         }
     }
 
-    fn try_read_prefixed(reg : &mut Registers, mem_bus: &MemBus) -> Option<Instruction>{
+    fn try_read_prefixed<B: Bus>(reg : &mut Registers, mem_bus: &B) -> Option<Instruction>{
         let byte = mem_bus.readb(reg.pc);
         reg.pc = reg.pc.wrapping_add(1);
         // let opcode = PrefixedOpcode::from(byte);
@@ -327,17 +339,17 @@ impl Instruction {
 }
 
 #[inline]
-fn read_next_byte_signed(pc: &mut u16, mem_bus: &MemBus) -> i8{
+fn read_next_byte_signed<B: Bus>(pc: &mut u16, mem_bus: &B) -> i8{
     u8::cast_signed(read_next_byte(pc, mem_bus))
 }
 
-fn read_next_byte(pc: &mut u16, mem_bus: &MemBus) -> u8{
+fn read_next_byte<B: Bus>(pc: &mut u16, mem_bus: &B) -> u8{
     let byte = mem_bus.readb(*pc);
     *pc = pc.wrapping_add(1);
     byte
 }
 
-fn read_next_word(pc: &mut u16, mem_bus: &MemBus) -> u16{
+fn read_next_word<B: Bus>(pc: &mut u16, mem_bus: &B) -> u16{
     let word = mem_bus.readw(*pc);
     *pc = pc.wrapping_add(2);
     word
@@ -18,4 +18,27 @@ impl From<Instruction> for IllegalInstructionErr{
     fn from(value: Instruction) -> Self {
         Self(value)
     }
-}
\ No newline at end of file
+}
+
+///Why the cpu could not run the instruction at `addr`
+#[derive(Debug, Clone, Copy)]
+pub enum CpuError {
+    ///One of the 11 opcodes the sm83 does not implement
+    IllegalOpcode { opcode: u8, addr: u16 },
+    ///An opcode the decoder does not handle
+    Decode { opcode: u8, addr: u16 },
+    ///The decoder produced an instruction the cpu cannot execute
+    Invariant { err: IllegalInstructionErr, addr: u16 },
+}
+
+impl Display for CpuError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CpuError::IllegalOpcode { opcode, addr } => write!(f, "Illegal opcode 0x{opcode:02X} at 0x{addr:04X}"),
+            CpuError::Decode { opcode, addr } => write!(f, "Could not decode opcode 0x{opcode:02X} at 0x{addr:04X}"),
+            CpuError::Invariant { err, addr } => write!(f, "{err} at 0x{addr:04X}"),
+        }
+    }
+}
+
+impl Error for CpuError {}
@@ -0,0 +1,165 @@
+use crate::{cpu::{Cpu, call_stack::FrameKind}, mem_bus::Bus};
+
+/// Cost in M-cycles of jumping to an interrupt handler
+pub const INTERRUPT_DISPATCH_CYCLES: u8 = 5;
+
+///Interrupt sources, in priority order
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Interrupt {
+    VBlank,
+    Stat,
+    Timer,
+    Serial,
+    Joypad,
+}
+
+impl Interrupt {
+    pub const ALL: [Interrupt; 5] = [
+        Interrupt::VBlank,
+        Interrupt::Stat,
+        Interrupt::Timer,
+        Interrupt::Serial,
+        Interrupt::Joypad,
+    ];
+
+    ///Bit of the interrupt in IF and IE
+    pub const fn mask(self) -> u8 {
+        1 << self as u8
+    }
+
+    ///Address of the interrupt handler
+    pub const fn vector(self) -> u16 {
+        0x40 + 8 * self as u16
+    }
+
+    ///Highest priority interrupt in a IF & IE value
+    pub fn from_pending(pending: u8) -> Option<Interrupt> {
+        Self::ALL.into_iter().find(|interrupt| pending & interrupt.mask() != 0)
+    }
+}
+
+impl<B: Bus> Cpu<B> {
+    ///Jump to the highest priority pending interrupt if IME is set,
+    ///returns the M-cycles spent doing so
+    pub fn handle_interrupts(&mut self) -> u8 {
+        let pending = self.mem_bus.pending_interrupts();
+
+        // any pending interrupt wakes the cpu, even when IME is not set
+        if pending != 0 {
+            self.halted = false;
+        }
+
+        if !self.ime {
+            return 0;
+        }
+        let Some(interrupt) = Interrupt::from_pending(pending) else {
+            return 0;
+        };
+
+        self.ime = false;
+        self.mem_bus.clear_interrupt(interrupt);
+        self.push_frame(FrameKind::Interrupt(interrupt), interrupt.vector());
+        self.reg.pc = interrupt.vector();
+
+        INTERRUPT_DISPATCH_CYCLES
+    }
+}
+
+//MARK: TEST
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        cpu::{Cpu, interrupts::Interrupt},
+        mem_bus::MemBus,
+    };
+
+    fn cpu_with_program(program: &[u8]) -> Cpu {
+        let mut cpu = Cpu::new(MemBus::from_bytes(program));
+        cpu.reg.sp = 0xD000;
+        cpu
+    }
+
+    #[test]
+    pub fn test_dispatch_priority() {
+        let mut cpu = cpu_with_program(&[0x00; 0x10]);
+        cpu.ime = true;
+        cpu.mem_bus.writeb(0xFFFF, 0x1F);
+        cpu.mem_bus.request_interrupt(Interrupt::Joypad);
+        cpu.mem_bus.request_interrupt(Interrupt::Timer);
+
+        cpu.step().unwrap();
+        assert_eq!(cpu.reg.pc, 0x50);
+        assert!(!cpu.ime);
+        assert_eq!(cpu.mem_bus.readb(0xFF0F) & 0x1F, Interrupt::Joypad.mask());
+        assert_eq!(cpu.mem_bus.readw(cpu.reg.sp), 0x0000);
+    }
+
+    #[test]
+    pub fn test_lock_ignores_interrupts() {
+        // EI, NOP, illegal 0xDD
+        let mut cpu = cpu_with_program(&[0xFB, 0x00, 0xDD]);
+        cpu.mem_bus.writeb(0xFFFF, 0x1F);
+        for _ in 0..3 {
+            cpu.step().unwrap();
+        }
+        assert!(cpu.locked.is_some());
+
+        cpu.mem_bus.request_interrupt(Interrupt::VBlank);
+        cpu.step().unwrap();
+        assert_eq!(cpu.reg.pc, 0x0003);
+        assert_eq!(cpu.mem_bus.readb(0xFF0F) & 0x1F, Interrupt::VBlank.mask());
+    }
+
+    #[test]
+    pub fn test_ei_delay() {
+        // EI, NOP, NOP
+        let mut cpu = cpu_with_program(&[0xFB, 0x00, 0x00]);
+        cpu.mem_bus.writeb(0xFFFF, 0x01);
+        cpu.mem_bus.request_interrupt(Interrupt::VBlank);
+
+        cpu.step().unwrap();
+        assert_eq!(cpu.reg.pc, 0x0001);
+        // the instruction following EI is always executed
+        cpu.step().unwrap();
+        assert_eq!(cpu.reg.pc, 0x0002);
+        cpu.step().unwrap();
+        assert_eq!(cpu.reg.pc, 0x0040);
+    }
+
+    #[test]
+    pub fn test_halt_wakes_without_ime() {
+        // HALT, NOP
+        let mut cpu = cpu_with_program(&[0x76, 0x00]);
+        cpu.mem_bus.writeb(0xFFFF, 0x04);
+
+        cpu.step().unwrap();
+        assert!(cpu.halted);
+        cpu.step().unwrap();
+        assert!(cpu.halted);
+        assert_eq!(cpu.reg.pc, 0x0001);
+
+        cpu.mem_bus.request_interrupt(Interrupt::Timer);
+        cpu.step().unwrap();
+        assert!(!cpu.halted);
+        assert_eq!(cpu.reg.pc, 0x0002);
+        // not serviced, IME is not set
+        assert_eq!(cpu.mem_bus.readb(0xFF0F) & 0x1F, Interrupt::Timer.mask());
+    }
+
+    #[test]
+    pub fn test_halt_bug() {
+        // HALT, INC A, NOP
+        let mut cpu = cpu_with_program(&[0x76, 0x3C, 0x00]);
+        cpu.mem_bus.writeb(0xFFFF, 0x04);
+        cpu.mem_bus.request_interrupt(Interrupt::Timer);
+
+        cpu.step().unwrap();
+        assert!(!cpu.halted);
+        // INC A is executed twice
+        cpu.step().unwrap();
+        cpu.step().unwrap();
+        assert_eq!(cpu.reg.a, 2);
+        assert_eq!(cpu.reg.pc, 0x0002);
+    }
+}
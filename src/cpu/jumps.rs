@@ -1,13 +1,14 @@
-use crate::cpu::{errors::IllegalInstructionErr, instructions::{JumpInstruction, JumpTarget, JumpTest}, Cpu};
+use crate::{cpu::{call_stack::FrameKind, errors::IllegalInstructionErr, instructions::{JumpInstruction, JumpTarget, JumpTest}, Cpu}, mem_bus::Bus};
 
-impl Cpu {
-    pub fn jump(&mut self, instruction :JumpInstruction,test: JumpTest,opt_target: Option<JumpTarget>) -> Result<(), IllegalInstructionErr>{
-        if !self.jump_test(test){return Ok(());}
+impl<B: Bus> Cpu<B> {
+    ///Returns whether the jump was taken
+    pub fn jump(&mut self, instruction :JumpInstruction,test: JumpTest,opt_target: Option<JumpTarget>) -> Result<bool, IllegalInstructionErr>{
+        if !self.jump_test(test){return Ok(false);}
         if let Some(target) = opt_target{
             match (instruction, target){
                 //Call
                 (JumpInstruction::Call , JumpTarget::Imm16(target)) =>
-                    self.call(target),
+                    self.call(target, FrameKind::Call),
                 //Jp
                 (JumpInstruction::Jp , JumpTarget::Imm16(target)) =>
                     self.jp(target),
@@ -17,7 +18,7 @@ impl Cpu {
                 (JumpInstruction::Jr , JumpTarget::ImmS8(offset)) =>
                     self.jr(offset),
                 (JumpInstruction::Rst, JumpTarget::Imm16(target)) =>
-                    self.call(target),
+                    self.call(target, FrameKind::Rst),
 
                 _=>Err(super::instructions::Instruction::Jump(instruction, test, opt_target))?
             }
@@ -34,7 +35,7 @@ impl Cpu {
 
         }
 
-        Ok(())
+        Ok(true)
     }
 
     fn jump_test(&self, test: JumpTest) -> bool{
@@ -47,9 +48,9 @@ impl Cpu {
         }
     }
 
-    fn call(&mut self, addr: u16){
-        self.mem_bus.writew(self.reg.sp, self.reg.pc);
-        self.reg.sp = self.reg.sp.wrapping_add(2);
+    ///PC already points past the operands, it is the return address
+    fn call(&mut self, addr: u16, kind: FrameKind){
+        self.push_frame(kind, addr);
         self.reg.pc = addr;
     }
 
@@ -62,14 +63,12 @@ impl Cpu {
     }
 
     fn ret(&mut self){
-        self.reg.pc = self.mem_bus.readw(self.reg.sp);
-        self.reg.pc = self.reg.pc.wrapping_add(2);
+        self.call_stack.pop(self.reg.sp);
+        self.reg.pc = self.pop_word();
     }
 
     fn reti(&mut self){
-        self.reg.pc = self.mem_bus.readw(self.reg.sp);
-        self.reg.pc = self.reg.pc.wrapping_add(2);
+        self.ret();
         self.ime = true;
-        self.halted = true;
     }
 }
\ No newline at end of file
@@ -1,7 +1,7 @@
-use crate::{cpu::{errors::IllegalInstructionErr, instructions::{ByteLoadDest, Instruction, LoadSrc, LoadDest, WordLoadDest}, Cpu}, utils::Value};
+use crate::{cpu::{errors::IllegalInstructionErr, instructions::{ByteLoadDest, Instruction, LoadSrc, LoadDest, WordLoadDest}, Cpu}, mem_bus::Bus, utils::Value};
 
 
-impl Cpu{
+impl<B: Bus> Cpu<B> {
     pub(super) fn load(&mut self, target: LoadDest, src: LoadSrc)->Result<(), IllegalInstructionErr>{
         match target{
             LoadDest::ByteDest(b_target) => {
@@ -1,6 +1,6 @@
-use crate::cpu::{instructions::MiscInstruction, Cpu};
+use crate::{cpu::{instructions::MiscInstruction, Cpu}, mem_bus::Bus};
 
-impl Cpu {
+impl<B: Bus> Cpu<B> {
     pub(super) fn misc(&mut self, instr : MiscInstruction) {
         match instr {
             MiscInstruction::Nop => (),
@@ -12,15 +12,20 @@ impl Cpu {
     }
 
     fn halt(&mut self) {
-        self.halted = true;
+        if !self.ime && self.mem_bus.pending_interrupts() != 0 {
+            self.halt_bug = true;
+        } else {
+            self.halted = true;
+        }
     }
 
     fn di(&mut self) {
         self.ime = false;
+        self.ei_pending = false;
     }
 
     fn ei(&mut self) {
-        self.ime = true;
+        self.ei_pending = true;
     }
 
     fn stop(&mut self) {
@@ -1,9 +1,18 @@
+use std::fmt::Display;
+
 use crate::{
-    cpu::{instructions::Instruction, registers::Registers},
-    mem_bus::MemBus,
+    cpu::{
+        call_stack::CallStack,
+        errors::IllegalInstructionErr,
+        instructions::Instruction,
+        opcode::{Opcode, opcode_cycles, prefixed_opcode_cycles},
+        registers::Registers,
+    },
+    mem_bus::{Bus, MemBus},
 };
 
 mod alu;
+pub mod call_stack;
 pub mod instructions;
 pub mod registers;
 mod decoder;
@@ -13,36 +22,96 @@ mod load;
 mod errors;
 mod stack;
 mod misc;
+pub mod interrupts;
+
+pub use errors::CpuError;
+#[cfg(test)]
+mod single_step_tests;
+
+/// `LD B,B` does nothing, test roms use it as a software breakpoint
+pub const BREAKPOINT_OPCODE: u8 = 0x40;
+
+///What the cpu does when it cannot run an instruction
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ErrorPolicy {
+    ///Freeze like the hardware does, only the clock keeps running. Emulator faults are returned once before freezing
+    #[default]
+    LockUp,
+    ///Refuse to go any further, every step returns the error
+    Stop,
+    ///Return the error and carry on with the next byte, illegal opcodes still lock the cpu
+    Report,
+}
+
+///The cpu ran one of the 11 illegal opcodes, like the hardware it hangs until a reset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lockup {
+    pub opcode: u8,
+    pub addr: u16,
+}
+
+impl Display for Lockup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "illegal opcode 0x{:02X} at 0x{:04X}", self.opcode, self.addr)
+    }
+}
+
+impl From<Lockup> for CpuError {
+    fn from(lockup: Lockup) -> Self {
+        CpuError::IllegalOpcode { opcode: lockup.opcode, addr: lockup.addr }
+    }
+}
+
+///What a successful `step` did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StepInfo {
+    ///Program counter before the step
+    pub pc: u16,
+    /// M-cycles it took
+    pub cycles: u8,
+}
 
 #[derive(Debug)]
-pub struct Cpu {
+pub struct Cpu<B = MemBus> {
     pub reg: Registers,
     pub halted: bool,
     pub ime:bool,
+    /// EI takes effect after the next instruction
+    pub ei_pending: bool,
+    /// HALT executed with IME unset and an interrupt pending: the next opcode is read twice
+    pub halt_bug: bool,
     pub low_pow : bool,
-    pub mem_bus: MemBus,
+    /// M-cycles elapsed since power on
+    pub cycles: u64,
+    /// Set when `LD B,B` runs, left for the caller to clear
+    pub breakpoint: bool,
+    pub on_error: ErrorPolicy,
+    /// Set once the cpu hung on an illegal opcode, it then ignores interrupts until a reset
+    pub locked: Option<Lockup>,
+    /// Emulator error that stopped or froze the cpu, depending on `on_error`
+    pub fault: Option<CpuError>,
+    pub call_stack: CallStack,
+    pub mem_bus: B,
 }
 
 #[allow(unused)]
-impl Cpu {
-    pub fn new(mem:MemBus)->Self {
+impl<B: Bus> Cpu<B> {
+    pub fn new(mem: B)->Self {
         let reg = Registers::zeroed();
-        Self { reg, halted: false, ime: false, low_pow: false, mem_bus: mem }
+        Self { reg, halted: false, ime: false, ei_pending: false, halt_bug: false, low_pow: false, cycles: 0, breakpoint: false, on_error: ErrorPolicy::default(), locked: None, fault: None, call_stack: CallStack::default(), mem_bus: mem }
     }
 
-    pub fn execute(&mut self, instruction: Instruction) {
+    ///Returns false if the instruction was a conditional jump that was not taken
+    pub fn execute(&mut self, instruction: Instruction) -> Result<bool, IllegalInstructionErr> {
         match instruction {
             Instruction::Arithmetic(instruction, imm, target) => {
-                self.alu(instruction, imm, target)
-                    .expect("Could not execute arithmetic instruction")
+                self.alu(instruction, imm, target)?
             }
             Instruction::Jump(instruction,test ,target ) =>{
-                self.jump(instruction, test, target)
-                    .expect("Could not execute jump instruction")
+                return self.jump(instruction, test, target);
             }
             Instruction::Load(target, src) => {
-                self.load(target, src)
-                    .expect("Could not execute load instruction")
+                self.load(target, src)?
             }
             Instruction::Stack(instr, reg) =>
                 self.stack(instr, reg),
@@ -50,41 +119,322 @@ impl Cpu {
             Instruction::Misc(instr) => 
                 self.misc(instr),
         }
+        Ok(true)
     }
 
-    pub fn step(&mut self){
-        if self.halted {return;}
-        if self.ime{
-            self.halted = true;
-        }
-        let instr_byte = self.mem_bus.readb(self.reg.pc);
+    ///Execute the next instruction, what happens on an error depends on `on_error`
+    pub fn step(&mut self) -> Result<StepInfo, CpuError> {
+        self.run_instruction(false)
+    }
 
-        if let Some(instruction) = Instruction::try_read(&mut self.reg, &self.mem_bus){
-            self.execute(instruction);
-        }else{
-            panic!("Cannot decode instruction :0x{:x}", instr_byte);
+    pub fn step_verbose(&mut self) -> Result<StepInfo, CpuError> {
+        self.run_instruction(true)
+    }
+
+    fn run_instruction(&mut self, verbose: bool) -> Result<StepInfo, CpuError> {
+        let pc = self.reg.pc;
+        if self.on_error == ErrorPolicy::Stop
+            && let Some(err) = self.fault.or(self.locked.map(CpuError::from))
+        {
+            return Err(err);
+        }
+        let result = if self.locked.is_some() || self.fault.is_some() {
+            // hung, interrupts are ignored and only the clock keeps running
+            Ok(1)
+        } else {
+            self.fetch_execute(verbose)
         };
 
+        // a failed instruction still spent its fetch
+        let cycles = *result.as_ref().unwrap_or(&1);
+        self.cycles += cycles as u64;
+        for _ in 0..cycles {
+            self.mem_bus.tick();
+        }
+
+        match result {
+            Ok(cycles) => Ok(StepInfo { pc, cycles }),
+            Err(err) => self.fail(err, StepInfo { pc, cycles }),
+        }
     }
 
+    ///Apply the error policy to a failed instruction
+    fn fail(&mut self, err: CpuError, info: StepInfo) -> Result<StepInfo, CpuError> {
+        match err {
+            // the hardware hangs on illegal opcodes, whatever the policy
+            CpuError::IllegalOpcode { opcode, addr } => {
+                self.locked = Some(Lockup { opcode, addr });
+                match self.on_error {
+                    ErrorPolicy::LockUp => Ok(info),
+                    _ => Err(err),
+                }
+            }
+            // an emulator bug, always returned so it does not pass for a frozen game
+            _ => {
+                if self.on_error != ErrorPolicy::Report {
+                    self.fault = Some(err);
+                }
+                Err(err)
+            }
+        }
+    }
 
-    pub fn step_verbose(&mut self) -> u16{
-        if self.halted {return self.reg.pc;}
-        if self.ime{
-            self.halted = true;
+    ///Service an interrupt, idle while halted or run one instruction, returns the M-cycles it took
+    fn fetch_execute(&mut self, verbose: bool) -> Result<u8, CpuError> {
+        let cycles = self.handle_interrupts();
+        if cycles != 0 {
+            return Ok(cycles);
+        }
+        if self.halted {
+            return Ok(1);
         }
-        let instr_byte = self.mem_bus.readb(self.reg.pc);
 
-        if let Some(instruction) = Instruction::try_read(&mut self.reg, &self.mem_bus){
-            println!("read : {instr_byte} => {instruction}");
-            self.execute(instruction);
-        }else{
-            panic!("Cannot decode instruction :0x{:x}", instr_byte);
+        if self.ei_pending {
+            self.ei_pending = false;
+            self.ime = true;
+        }
+
+        let addr = self.reg.pc;
+        let instr_byte = self.mem_bus.readb(addr);
+        let operand_offset = if self.halt_bug { 0 } else { 1 };
+        let prefixed_byte = self.mem_bus.readb(addr.wrapping_add(operand_offset));
+        let instruction = if self.halt_bug {
+            self.halt_bug = false;
+            Instruction::try_read_halt_bug(&mut self.reg, &self.mem_bus)
+        } else {
+            Instruction::try_read(&mut self.reg, &self.mem_bus)
+        };
+        let Some(instruction) = instruction else {
+            return Err(match Opcode::try_from(instr_byte) {
+                Err(_) => CpuError::IllegalOpcode { opcode: instr_byte, addr },
+                Ok(_) => CpuError::Decode { opcode: instr_byte, addr },
+            });
         };
 
-        return self.reg.pc;
+        if verbose {
+            println!("read : {instr_byte} => {instruction}");
+        }
+        let branch = self.execute(instruction).map_err(|err| CpuError::Invariant { err, addr })?;
+        if instr_byte == BREAKPOINT_OPCODE {
+            self.breakpoint = true;
+        }
+
+        Ok(if instr_byte == 0xCB {
+            prefixed_opcode_cycles(prefixed_byte)
+        } else {
+            opcode_cycles(instr_byte, branch)
+        })
+    }
+}
+
+//MARK: TEST
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+
+    use crate::{
+        cpu::{Cpu, CpuError, ErrorPolicy, Lockup, StepInfo, call_stack::FrameKind},
+        mem_bus::{Bus, MemBus},
+    };
+
+    #[derive(Debug, PartialEq)]
+    enum Access {
+        Read(u16),
+        Write(u16, u8),
+        Tick,
+    }
 
+    ///64 KiB of ram logging every access and M-cycle in order
+    struct RecordingBus {
+        ram: Box<[u8; 0x10000]>,
+        log: RefCell<Vec<Access>>,
     }
 
-    
+    impl RecordingBus {
+        fn new(program: &[u8]) -> Self {
+            let mut ram = Box::new([0; 0x10000]);
+            ram[..program.len()].copy_from_slice(program);
+            RecordingBus { ram, log: RefCell::new(vec![]) }
+        }
+
+        fn take_log(&mut self) -> Vec<Access> {
+            std::mem::take(self.log.get_mut())
+        }
+    }
+
+    impl Bus for RecordingBus {
+        fn readb(&self, addr: u16) -> u8 {
+            self.log.borrow_mut().push(Access::Read(addr));
+            self.ram[addr as usize]
+        }
+
+        fn writeb(&mut self, addr: u16, byte: u8) {
+            self.log.get_mut().push(Access::Write(addr, byte));
+            self.ram[addr as usize] = byte;
+        }
+
+        fn tick(&mut self) {
+            self.log.get_mut().push(Access::Tick);
+        }
+
+        // the interrupt registers are not part of what an instruction does on the bus
+        fn pending_interrupts(&self) -> u8 {
+            0
+        }
+    }
+
+    #[test]
+    pub fn test_cycle_counting() {
+        // NOP, JR NZ +0, JR Z +0, CALL 0x0010, ..., BIT 0 [HL]
+        let mut program = vec![0x00, 0x20, 0x00, 0x28, 0x00, 0xCD, 0x10, 0x00];
+        program.resize(0x10, 0x00);
+        program.extend_from_slice(&[0xCB, 0x46]);
+        let mut cpu = Cpu::new(MemBus::from_bytes(&program));
+        cpu.reg.sp = 0xD000;
+
+        assert_eq!(cpu.step().unwrap().cycles, 1);
+        // Z is clear: the first jump is taken, not the second one
+        assert_eq!(cpu.step().unwrap().cycles, 3);
+        assert_eq!(cpu.step().unwrap().cycles, 2);
+        assert_eq!(cpu.step().unwrap().cycles, 6);
+        assert_eq!(cpu.step().unwrap().cycles, 3);
+
+        assert_eq!(cpu.cycles, 15);
+    }
+
+    #[test]
+    pub fn test_bus_accesses() {
+        use Access::*;
+
+        // LD [HL],A then CALL 0x0010
+        let mut cpu = Cpu::new(RecordingBus::new(&[0x77, 0xCD, 0x10, 0x00]));
+        cpu.reg.a = 0x42;
+        cpu.reg.set_hl(0xC000);
+        cpu.reg.sp = 0xD000;
+
+        // the opcode and the byte after it are peeked before decoding, the ticks come after all the accesses
+        cpu.step().unwrap();
+        let log = cpu.mem_bus.take_log();
+        assert_eq!(log, [Read(0x0000), Read(0x0001), Read(0x0000), Write(0xC000, 0x42), Tick, Tick]);
+
+        cpu.step().unwrap();
+        let log = cpu.mem_bus.take_log();
+        let (accesses, ticks) = log.split_at(7);
+        assert_eq!(
+            accesses,
+            [
+                Read(0x0001),
+                Read(0x0002),
+                Read(0x0001),
+                Read(0x0002),
+                Read(0x0003),
+                Write(0xCFFF, 0x00),
+                Write(0xCFFE, 0x04)
+            ]
+        );
+        assert_eq!(ticks, [Tick, Tick, Tick, Tick, Tick, Tick]);
+    }
+
+    #[test]
+    pub fn test_software_breakpoint() {
+        // NOP, LD B,B
+        let mut cpu = Cpu::new(MemBus::from_bytes(&[0x00, 0x40]));
+
+        cpu.step().unwrap();
+        assert!(!cpu.breakpoint);
+        cpu.step().unwrap();
+        assert!(cpu.breakpoint);
+    }
+
+    #[test]
+    pub fn test_error_policies() {
+        // NOP, illegal 0xD3, NOP
+        let rom = [0x00, 0xD3, 0x00];
+
+        let mut cpu = Cpu::new(MemBus::from_bytes(&rom));
+        cpu.on_error = ErrorPolicy::Report;
+        cpu.step().unwrap();
+        let err = cpu.step().unwrap_err();
+        assert!(matches!(err, CpuError::IllegalOpcode { opcode: 0xD3, addr: 0x0001 }));
+        // still locked, but only reported once
+        assert_eq!(cpu.step().unwrap(), StepInfo { pc: 0x0002, cycles: 1 });
+        assert_eq!(cpu.step().unwrap(), StepInfo { pc: 0x0002, cycles: 1 });
+
+        let mut cpu = Cpu::new(MemBus::from_bytes(&rom));
+        cpu.on_error = ErrorPolicy::Stop;
+        cpu.step().unwrap();
+        assert!(cpu.step().is_err());
+        let cycles = cpu.cycles;
+        assert!(cpu.step().is_err());
+        assert_eq!(cpu.cycles, cycles);
+
+        // the default: the cpu hangs and only burns cycles
+        let mut cpu = Cpu::new(MemBus::from_bytes(&rom));
+        cpu.step().unwrap();
+        cpu.step().unwrap();
+        let pc = cpu.reg.pc;
+        assert_eq!(cpu.step().unwrap(), StepInfo { pc, cycles: 1 });
+        assert_eq!(cpu.reg.pc, pc);
+        assert_eq!(cpu.locked, Some(Lockup { opcode: 0xD3, addr: 0x0001 }));
+        // a hardware lock up is not an emulator fault
+        assert!(cpu.fault.is_none());
+    }
+
+    #[test]
+    pub fn test_faults_reported() {
+        // no opcode reaches a decode error, fake one on a NOP
+        let fault = CpuError::Decode { opcode: 0x00, addr: 0x0000 };
+        let info = StepInfo { pc: 0x0000, cycles: 1 };
+
+        for policy in [ErrorPolicy::LockUp, ErrorPolicy::Stop, ErrorPolicy::Report] {
+            let mut cpu = Cpu::new(MemBus::from_bytes(&[0x00, 0x00]));
+            cpu.on_error = policy;
+            assert!(matches!(cpu.fail(fault, info), Err(CpuError::Decode { .. })), "{policy:?}");
+        }
+
+        // the default policy reports it once, then hangs
+        let mut cpu = Cpu::new(MemBus::from_bytes(&[0x00, 0x00]));
+        assert!(cpu.fail(fault, info).is_err());
+        assert_eq!(cpu.step().unwrap(), StepInfo { pc: 0x0000, cycles: 1 });
+        assert_eq!(cpu.reg.pc, 0x0000);
+    }
+
+    #[test]
+    pub fn test_illegal_opcodes_lock() {
+        for opcode in [0xD3, 0xDB, 0xDD, 0xE3, 0xE4, 0xEB, 0xEC, 0xED, 0xF4, 0xFC, 0xFD] {
+            let mut cpu = Cpu::new(MemBus::from_bytes(&[opcode]));
+            cpu.step().unwrap();
+            assert_eq!(cpu.locked, Some(Lockup { opcode, addr: 0x0000 }), "0x{opcode:02X}");
+            assert!(!cpu.halted && !cpu.low_pow);
+        }
+    }
+
+    #[test]
+    pub fn test_call_rst_ret() {
+        // CALL 0x0010, ..., RST 0x28, RET, ..., RET
+        let mut program = vec![0xCD, 0x10, 0x00];
+        program.resize(0x10, 0x00);
+        program.extend_from_slice(&[0xEF, 0xC9]);
+        program.resize(0x28, 0x00);
+        program.push(0xC9);
+        let mut cpu = Cpu::new(MemBus::from_bytes(&program));
+        cpu.reg.sp = 0xD000;
+
+        cpu.step().unwrap();
+        assert_eq!((cpu.reg.pc, cpu.reg.sp), (0x0010, 0xCFFE));
+        assert_eq!(cpu.mem_bus.readw(0xCFFE), 0x0003);
+        cpu.step().unwrap();
+        assert_eq!((cpu.reg.pc, cpu.reg.sp), (0x0028, 0xCFFC));
+        assert_eq!(cpu.mem_bus.readw(0xCFFC), 0x0011);
+        let kinds: Vec<_> = cpu.call_stack.frames().map(|frame| frame.kind).collect();
+        assert_eq!(kinds, [FrameKind::Rst, FrameKind::Call]);
+
+        cpu.step().unwrap();
+        assert_eq!((cpu.reg.pc, cpu.reg.sp), (0x0011, 0xCFFE));
+        assert_eq!(cpu.call_stack.frames().count(), 1);
+        cpu.step().unwrap();
+        assert_eq!((cpu.reg.pc, cpu.reg.sp), (0x0003, 0xD000));
+        assert_eq!(cpu.call_stack.frames().count(), 0);
+    }
 }
@@ -38,3 +38,16 @@ include! {concat!(env!("OUT_DIR"), "/opcode_rs/byte_to_opcode.rs")}
 include! {concat!(env!("OUT_DIR"), "/opcode_rs/mnemonic_enum.rs")}
 
 include! {concat!(env!("OUT_DIR"), "/opcode_rs/opcode_to_mnemonics.rs")}
+
+include! {concat!(env!("OUT_DIR"), "/opcode_rs/cycles.rs")}
+
+///M-cycles taken by an unprefixed opcode, branch tells if a conditional jump was taken
+pub const fn opcode_cycles(byte: u8, branch: bool) -> u8 {
+    let (taken, not_taken) = OPCODE_CYCLES[byte as usize];
+    if branch { taken } else { not_taken }
+}
+
+///M-cycles taken by a 0xCB prefixed opcode, including the prefix
+pub const fn prefixed_opcode_cycles(byte: u8) -> u8 {
+    PREFIXED_OPCODE_CYCLES[byte as usize]
+}
@@ -20,6 +20,11 @@ impl Registers {
     pub fn zeroed() -> Self{
         Self { a: 0, f: 0, b: 0, c: 0, d: 0, e: 0, h: 0, l: 0, sp: 0, pc: 0 }
     }
+
+    ///State left by the DMG boot rom when it jumps to the cartridge
+    pub fn post_boot() -> Self {
+        Self { a: 0x01, f: 0xB0, b: 0x00, c: 0x13, d: 0x00, e: 0xD8, h: 0x01, l: 0x4D, sp: 0xFFFE, pc: 0x0100 }
+    }
 }
 
 // -- getters --
@@ -42,9 +47,10 @@ impl Registers {
 }
 // -- setters --
 impl Registers {
+    ///The low nibble of F does not exist, it always reads 0
     pub const fn set_af(&mut self, word: u16) {
         self.a = ((word & 0xFF00) >> 8) as u8;
-        self.f = (word & 0x00FF) as u8;
+        self.f = (word & 0x00F0) as u8;
     }
 
     pub const fn set_bc(&mut self, word: u16) {
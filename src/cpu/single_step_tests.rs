@@ -0,0 +1,237 @@
+//! Runner for the sm83 per-opcode test vectors of SingleStepTests,
+//! run them with `SM83_TESTS=<dir of the xx.json / cb xx.json files> cargo test -- --ignored`.
+//!
+//! This is a unit test module rather than an integration test under `tests/`:
+//! gb_emu is a binary crate only, so an outside test could not reach `Cpu` and `Bus`.
+
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::{
+    cpu::{Cpu, ErrorPolicy, registers::Registers},
+    mem_bus::Bus,
+};
+
+/// Failures printed per file, the rest are only counted
+const SHOWN_FAILURES: usize = 5;
+
+///64 KiB of plain ram, nothing mapped, recording the M-cycles and the writes the cpu made
+struct FlatBus {
+    ram: Box<[u8; 0x10000]>,
+    ticks: usize,
+    writes: Vec<(u16, u8)>,
+}
+
+impl Bus for FlatBus {
+    fn readb(&self, addr: u16) -> u8 {
+        self.ram[addr as usize]
+    }
+
+    fn writeb(&mut self, addr: u16, byte: u8) {
+        self.ram[addr as usize] = byte;
+        self.writes.push((addr, byte));
+    }
+
+    fn tick(&mut self) {
+        self.ticks += 1;
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TestCase {
+    name: String,
+    initial: State,
+    #[serde(rename = "final")]
+    expected: State,
+    cycles: Vec<Option<Cycle>>,
+}
+
+///One M-cycle on the bus: address, data and the `rwm` pins, null when the bus is idle
+#[derive(Debug, Deserialize)]
+struct Cycle(Option<u16>, Option<u8>, String);
+
+impl Cycle {
+    fn write(&self) -> Option<(u16, u8)> {
+        match self {
+            Cycle(Some(addr), Some(byte), pins) if pins.contains('w') => Some((*addr, *byte)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct State {
+    pc: u16,
+    sp: u16,
+    a: u8,
+    b: u8,
+    c: u8,
+    d: u8,
+    e: u8,
+    f: u8,
+    h: u8,
+    l: u8,
+    ime: u8,
+    #[serde(default)]
+    ie: Option<u8>,
+    ram: Vec<(u16, u8)>,
+}
+
+impl State {
+    fn registers(&self) -> Registers {
+        let mut reg = Registers::zeroed();
+        (reg.a, reg.f, reg.b, reg.c) = (self.a, self.f, self.b, self.c);
+        (reg.d, reg.e, reg.h, reg.l) = (self.d, self.e, self.h, self.l);
+        (reg.sp, reg.pc) = (self.sp, self.pc);
+        reg
+    }
+}
+
+///Run one `Cpu::step` from the initial state, returns the differences with the final state
+fn run_case(case: &TestCase) -> Result<(), String> {
+    let mut bus = FlatBus { ram: Box::new([0; 0x10000]), ticks: 0, writes: vec![] };
+    for (addr, byte) in &case.initial.ram {
+        bus.ram[*addr as usize] = *byte;
+    }
+    if let Some(ie) = case.initial.ie {
+        bus.ram[0xFFFF] = ie;
+    }
+
+    let mut cpu = Cpu::new(bus);
+    cpu.reg = case.initial.registers();
+    cpu.ime = case.initial.ime != 0;
+    cpu.on_error = ErrorPolicy::Report;
+
+    cpu.step().map_err(|err| err.to_string())?;
+
+    let mut diffs = vec![];
+    let (expected, found) = (case.expected.registers(), &cpu.reg);
+    let registers = [
+        ("a", expected.a as u16, found.a as u16),
+        ("f", expected.f as u16, found.f as u16),
+        ("b", expected.b as u16, found.b as u16),
+        ("c", expected.c as u16, found.c as u16),
+        ("d", expected.d as u16, found.d as u16),
+        ("e", expected.e as u16, found.e as u16),
+        ("h", expected.h as u16, found.h as u16),
+        ("l", expected.l as u16, found.l as u16),
+        ("sp", expected.sp, found.sp),
+        ("pc", expected.pc, found.pc),
+    ];
+    for (name, expected, found) in registers {
+        if expected != found {
+            diffs.push(format!("{name} : expected 0x{expected:02X}, found 0x{found:02X}"));
+        }
+    }
+    if (case.expected.ime != 0) != cpu.ime {
+        diffs.push(format!("ime : expected {}, found {}", case.expected.ime, cpu.ime as u8));
+    }
+    for (addr, byte) in &case.expected.ram {
+        let found = cpu.mem_bus.ram[*addr as usize];
+        if found != *byte {
+            diffs.push(format!("[0x{addr:04X}] : expected 0x{byte:02X}, found 0x{found:02X}"));
+        }
+    }
+
+
+    // accesses are not yet spread over the M-cycles, only their count and the writes are checked
+    if cpu.mem_bus.ticks != case.cycles.len() {
+        diffs.push(format!("cycles : expected {}, found {}", case.cycles.len(), cpu.mem_bus.ticks));
+    }
+    let writes: Vec<_> = case.cycles.iter().flatten().filter_map(Cycle::write).collect();
+    if cpu.mem_bus.writes != writes {
+        diffs.push(format!("writes : expected {writes:X?}, found {:X?}", cpu.mem_bus.writes));
+    }
+
+    if diffs.is_empty() { Ok(()) } else { Err(diffs.join(", ")) }
+}
+
+///Run every case of a file, returns the number of failures
+fn run_file(path: &Path) -> usize {
+    let json = std::fs::read_to_string(path).expect("could not read the test file");
+    let cases: Vec<TestCase> = serde_json::from_str(&json).expect("invalid test file");
+
+    let failures: Vec<_> = cases
+        .iter()
+        .filter_map(|case| run_case(case).err().map(|diff| (&case.name, diff)))
+        .collect();
+
+    println!("{} : {}/{} passed", path.display(), cases.len() - failures.len(), cases.len());
+    for (name, diff) in failures.iter().take(SHOWN_FAILURES) {
+        println!("    {name} : {diff}");
+    }
+    failures.len()
+}
+
+//MARK: TEST
+
+///Hand written cases in the SingleStepTests format, so the runner itself is checked without the vectors
+const SAMPLE_CASES: &str = r#"[
+    {
+        "name": "00 nop",
+        "initial": {"pc": 49152, "sp": 65534, "a": 1, "b": 2, "c": 3, "d": 4, "e": 5, "f": 176, "h": 6, "l": 7, "ime": 0, "ie": 0, "ram": [[49152, 0]]},
+        "final": {"pc": 49153, "sp": 65534, "a": 1, "b": 2, "c": 3, "d": 4, "e": 5, "f": 176, "h": 6, "l": 7, "ime": 0, "ie": 0, "ram": [[49152, 0]]},
+        "cycles": [[49152, 0, "r-m"]]
+    },
+    {
+        "name": "41 ld b,c",
+        "initial": {"pc": 49152, "sp": 65534, "a": 1, "b": 2, "c": 3, "d": 4, "e": 5, "f": 0, "h": 6, "l": 7, "ime": 0, "ie": 0, "ram": [[49152, 65]]},
+        "final": {"pc": 49153, "sp": 65534, "a": 1, "b": 3, "c": 3, "d": 4, "e": 5, "f": 0, "h": 6, "l": 7, "ime": 0, "ie": 0, "ram": [[49152, 65]]},
+        "cycles": [[49152, 65, "r-m"]]
+    },
+    {
+        "name": "80 add a,b",
+        "initial": {"pc": 49152, "sp": 65534, "a": 58, "b": 198, "c": 0, "d": 0, "e": 0, "f": 0, "h": 0, "l": 0, "ime": 0, "ie": 0, "ram": [[49152, 128]]},
+        "final": {"pc": 49153, "sp": 65534, "a": 0, "b": 198, "c": 0, "d": 0, "e": 0, "f": 176, "h": 0, "l": 0, "ime": 0, "ie": 0, "ram": [[49152, 128]]},
+        "cycles": [[49152, 128, "r-m"]]
+    },
+    {
+        "name": "77 ld [hl],a",
+        "initial": {"pc": 49152, "sp": 65534, "a": 66, "b": 0, "c": 0, "d": 0, "e": 0, "f": 0, "h": 208, "l": 16, "ime": 0, "ie": 0, "ram": [[49152, 119], [53264, 0]]},
+        "final": {"pc": 49153, "sp": 65534, "a": 66, "b": 0, "c": 0, "d": 0, "e": 0, "f": 0, "h": 208, "l": 16, "ime": 0, "ie": 0, "ram": [[49152, 119], [53264, 66]]},
+        "cycles": [[49152, 119, "r-m"], [53264, 66, "-wm"]]
+    },
+    {
+        "name": "c5 push bc",
+        "initial": {"pc": 49152, "sp": 53248, "a": 0, "b": 18, "c": 52, "d": 0, "e": 0, "f": 0, "h": 0, "l": 0, "ime": 0, "ie": 0, "ram": [[49152, 197]]},
+        "final": {"pc": 49153, "sp": 53246, "a": 0, "b": 18, "c": 52, "d": 0, "e": 0, "f": 0, "h": 0, "l": 0, "ime": 0, "ie": 0, "ram": [[49152, 197], [53247, 18], [53246, 52]]},
+        "cycles": [[49152, 197, "r-m"], [49153, null, "---"], [53247, 18, "-wm"], [53246, 52, "-wm"]]
+    },
+    {
+        "name": "cd call a16",
+        "initial": {"pc": 49152, "sp": 53248, "a": 0, "b": 0, "c": 0, "d": 0, "e": 0, "f": 0, "h": 0, "l": 0, "ime": 0, "ie": 0, "ram": [[49152, 205], [49153, 0], [49154, 193]]},
+        "final": {"pc": 49408, "sp": 53246, "a": 0, "b": 0, "c": 0, "d": 0, "e": 0, "f": 0, "h": 0, "l": 0, "ime": 0, "ie": 0, "ram": [[49152, 205], [49153, 0], [49154, 193], [53247, 192], [53246, 3]]},
+        "cycles": [[49152, 205, "r-m"], [49153, 0, "r-m"], [49154, 193, "r-m"], [49154, null, "---"], [53247, 192, "-wm"], [53246, 3, "-wm"]]
+    },
+    {
+        "name": "f1 pop af",
+        "initial": {"pc": 49152, "sp": 53246, "a": 0, "b": 0, "c": 0, "d": 0, "e": 0, "f": 0, "h": 0, "l": 0, "ime": 0, "ie": 0, "ram": [[49152, 241], [53246, 255], [53247, 18]]},
+        "final": {"pc": 49153, "sp": 53248, "a": 18, "b": 0, "c": 0, "d": 0, "e": 0, "f": 240, "h": 0, "l": 0, "ime": 0, "ie": 0, "ram": [[49152, 241], [53246, 255], [53247, 18]]},
+        "cycles": [[49152, 241, "r-m"], [53246, 255, "r-m"], [53247, 18, "r-m"]]
+    }
+]"#;
+
+#[test]
+pub fn test_sample_cases() {
+    let cases: Vec<TestCase> = serde_json::from_str(SAMPLE_CASES).unwrap();
+    for case in &cases {
+        assert_eq!(run_case(case), Ok(()), "{}", case.name);
+    }
+}
+
+#[test]
+#[ignore = "needs the SingleStepTests sm83 vectors in SM83_TESTS"]
+pub fn test_sm83_vectors() {
+    let dir = std::env::var("SM83_TESTS").expect("SM83_TESTS is not set");
+
+    let mut files: Vec<_> = std::fs::read_dir(&dir)
+        .expect("could not read SM83_TESTS")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    files.sort();
+
+    let failures: usize = files.iter().map(|file| run_file(file)).sum();
+    assert_eq!(failures, 0, "{failures} sm83 test cases failed");
+}
@@ -1,7 +1,7 @@
-use crate::cpu::{instructions::{StackInstruction, StackReg16}, Cpu};
+use crate::{cpu::{call_stack::{Frame, FrameKind}, instructions::{StackInstruction, StackReg16}, Cpu}, mem_bus::Bus, utils::word_to_bytes};
 
 
-impl Cpu{
+impl<B: Bus> Cpu<B> {
     pub fn stack(&mut self, instruction: StackInstruction, reg: StackReg16) {
         match instruction {
             StackInstruction::Push => self.push(reg),
@@ -10,14 +10,33 @@ impl Cpu{
     }
 
     fn push(&mut self, reg: StackReg16){
-        self.reg.sp = self.reg.sp.wrapping_sub(2);
-        self.mem_bus.writew(self.reg.sp, self.get_reg_value(reg));
+        self.push_word(self.get_reg_value(reg));
+    }
+
+    ///The high byte goes first, to SP-1, then the low byte to SP-2
+    pub(super) fn push_word(&mut self, word: u16){
+        let (low, high) = word_to_bytes(word);
+        self.reg.sp = self.reg.sp.wrapping_sub(1);
+        self.mem_bus.writeb(self.reg.sp, high);
+        self.reg.sp = self.reg.sp.wrapping_sub(1);
+        self.mem_bus.writeb(self.reg.sp, low);
+    }
+
+    ///Push PC before jumping to `target`, and remember it in the call stack
+    pub(super) fn push_frame(&mut self, kind: FrameKind, target: u16){
+        self.push_word(self.reg.pc);
+        self.call_stack.push(Frame { kind, target, return_addr: self.reg.pc, sp: self.reg.sp });
+    }
+
+    pub(super) fn pop_word(&mut self) -> u16{
+        let word = self.mem_bus.readw(self.reg.sp);
+        self.reg.sp = self.reg.sp.wrapping_add(2);
+        word
     }
 
     fn pop(&mut self, reg: StackReg16){
-        let value = self.mem_bus.readw(self.reg.sp);
+        let value = self.pop_word();
         self.set_reg(reg, value);
-        self.reg.sp = self.reg.sp.wrapping_add(2);
     }
 
     fn get_reg_value(&self, reg: StackReg16) -> u16{
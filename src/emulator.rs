@@ -0,0 +1,122 @@
+use crate::{
+    cpu::{Cpu, CpuError, registers::Registers},
+    graphics::FRAME_SIZE,
+    mem_bus::{
+        MemBus,
+        io::{joypad::Button, serial::Loopback},
+    },
+};
+
+/// M-cycles in a frame, 154 lines of 114 M-cycles
+pub const CYCLES_PER_FRAME: u64 = 154 * 114;
+
+///Registers set by the DMG boot rom before it gives control to the cartridge
+const POST_BOOT_IO: [(u16, u8); 4] = [(0xFF40, 0x91), (0xFF47, 0xFC), (0xFF48, 0xFF), (0xFF49, 0xFF)];
+
+///A whole console, started as if the boot rom just ran
+#[derive(Debug)]
+pub struct Emulator {
+    pub cpu: Cpu,
+}
+
+impl Emulator {
+    pub fn new(mut mem_bus: MemBus) -> Self {
+        for (addr, byte) in POST_BOOT_IO {
+            mem_bus.writeb(addr, byte);
+        }
+        let mut cpu = Cpu::new(mem_bus);
+        cpu.reg = Registers::post_boot();
+        Self { cpu }
+    }
+
+    ///Run until the PPU completes a frame, or for a frame worth of cycles if the lcd is off
+    pub fn run_frame(&mut self) -> Result<(), CpuError> {
+        let frame = self.cpu.mem_bus.ppu().frame_count();
+        let start = self.cpu.cycles;
+
+        while self.cpu.mem_bus.ppu().frame_count() == frame && self.cpu.cycles - start < CYCLES_PER_FRAME {
+            self.cpu.step()?;
+        }
+        Ok(())
+    }
+
+    ///Plug a cable between the link ports of two emulators, replacing whatever was plugged in
+    pub fn link(&mut self, other: &mut Emulator) {
+        let (ours, theirs) = Loopback::pair();
+        self.cpu.mem_bus.set_serial_link(Box::new(ours));
+        other.cpu.mem_bus.set_serial_link(Box::new(theirs));
+    }
+
+    pub fn press(&mut self, button: Button) {
+        self.cpu.mem_bus.joypad_mut().press(button);
+    }
+
+    pub fn release(&mut self, button: Button) {
+        self.cpu.mem_bus.joypad_mut().release(button);
+    }
+
+    ///Last frame drawn by the PPU, as `PIX_*` values
+    pub fn framebuffer(&self) -> &[u8; FRAME_SIZE] {
+        self.cpu.mem_bus.ppu().framebuffer()
+    }
+}
+
+//MARK: TEST
+
+#[cfg(test)]
+mod test {
+    use crate::{emulator::Emulator, mem_bus::MemBus};
+
+    ///`JR -2` at the entry point, with the lcd enabled by the boot state
+    fn looping_rom() -> Vec<u8> {
+        let mut rom = vec![0; 0x8000];
+        rom[0x100] = 0x18;
+        rom[0x101] = 0xFE;
+        rom
+    }
+
+    #[test]
+    pub fn test_run_frame() {
+        let mut emulator = Emulator::new(MemBus::from_bytes(&looping_rom()));
+        assert_eq!(emulator.cpu.reg.pc, 0x100);
+
+        emulator.run_frame().unwrap();
+        emulator.run_frame().unwrap();
+        assert_eq!(emulator.cpu.mem_bus.ppu().frame_count(), 2);
+        assert_eq!(emulator.cpu.mem_bus.readb(0xFF44), 144);
+    }
+
+    ///Put `sb` in SB then start a transfer with `sc`, using `LD [a16],A` so LDH is not involved
+    fn serial_rom(sb: u8, sc: u8) -> Vec<u8> {
+        let mut rom = looping_rom();
+        let program = [0x3E, sb, 0xEA, 0x01, 0xFF, 0x3E, sc, 0xEA, 0x02, 0xFF, 0x18, 0xFE];
+        rom[0x100..0x100 + program.len()].copy_from_slice(&program);
+        rom
+    }
+
+    #[test]
+    pub fn test_link() {
+        let mut master = Emulator::new(MemBus::from_bytes(&serial_rom(0x42, 0x81)));
+        let mut slave = Emulator::new(MemBus::from_bytes(&serial_rom(0x24, 0x80)));
+        master.link(&mut slave);
+
+        // the slave has to wait on the master clock before the master shifts
+        slave.run_frame().unwrap();
+        master.run_frame().unwrap();
+        slave.run_frame().unwrap();
+
+        assert_eq!(master.cpu.mem_bus.readb(0xFF01), 0x24);
+        assert_eq!(slave.cpu.mem_bus.readb(0xFF01), 0x42);
+        assert_eq!(slave.cpu.mem_bus.readb(0xFF02) & 0x80, 0);
+    }
+
+    #[test]
+    pub fn test_run_frame_lcd_off() {
+        let mut emulator = Emulator::new(MemBus::from_bytes(&looping_rom()));
+        emulator.cpu.mem_bus.writeb(0xFF40, 0x00);
+
+        emulator.run_frame().unwrap();
+        assert_eq!(emulator.cpu.mem_bus.ppu().frame_count(), 0);
+        assert!(emulator.cpu.cycles >= super::CYCLES_PER_FRAME);
+    }
+}
@@ -0,0 +1,633 @@
+use std::{error::Error, fmt::Display, io::Write, path::Path};
+
+use crate::graphics::{FRAME_SIZE, PIX_BLACK, PIX_DARK_GRAY, PIX_LIGHT_GRAY, PIX_WHITE, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+/// Largest payload of a stored deflate block
+const STORED_BLOCK_SIZE: usize = 0xFFFF;
+
+// deflate length and distance codes, from 257 and 0
+const LENGTH_BASE: [u16; 29] =
+    [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145,
+    8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] =
+    [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+/// Order in which the code lengths of the code length alphabet are stored
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+///RGB colors given to each `PIX_*` shade
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Palette {
+    colors: [[u8; 3]; 4],
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::new([[0xFF, 0xFF, 0xFF], [0xAA, 0xAA, 0xAA], [0x55, 0x55, 0x55], [0x00, 0x00, 0x00]])
+    }
+}
+
+impl Palette {
+    ///Colors from the lightest to the darkest shade
+    pub fn new([white, light_gray, dark_gray, black]: [[u8; 3]; 4]) -> Self {
+        let mut colors = [[0; 3]; 4];
+        colors[PIX_WHITE as usize] = white;
+        colors[PIX_LIGHT_GRAY as usize] = light_gray;
+        colors[PIX_DARK_GRAY as usize] = dark_gray;
+        colors[PIX_BLACK as usize] = black;
+        Self { colors }
+    }
+
+    ///Parse four comma separated hex colors, from the lightest to the darkest: `e0f8d0,88c070,346856,081820`
+    pub fn parse(s: &str) -> Result<Self, PaletteError> {
+        let colors = s
+            .split(',')
+            .map(|color| {
+                let color = color.trim().trim_start_matches('#');
+                match u32::from_str_radix(color, 16) {
+                    Ok(rgb) if color.len() == 6 => Ok([(rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8]),
+                    _ => Err(PaletteError::InvalidColor(color.to_string())),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let colors: [[u8; 3]; 4] = colors.try_into().map_err(|colors: Vec<_>| PaletteError::WrongCount(colors.len()))?;
+        Ok(Self::new(colors))
+    }
+
+    pub fn color(&self, pix: u8) -> [u8; 3] {
+        self.colors[(pix & 0b11) as usize]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImageFormat {
+    Png,
+    Ppm,
+}
+
+impl ImageFormat {
+    ///Guess the format from the file extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "png" => Some(Self::Png),
+            "ppm" => Some(Self::Ppm),
+            _ => None,
+        }
+    }
+
+    pub fn write(self, writer: &mut impl Write, frame: &[u8; FRAME_SIZE], palette: &Palette) -> std::io::Result<()> {
+        match self {
+            Self::Png => write_png(writer, frame, palette),
+            Self::Ppm => write_ppm(writer, frame, palette),
+        }
+    }
+}
+
+///Binary PPM (P6)
+pub fn write_ppm(writer: &mut impl Write, frame: &[u8; FRAME_SIZE], palette: &Palette) -> std::io::Result<()> {
+    write!(writer, "P6\n{SCREEN_WIDTH} {SCREEN_HEIGHT}\n255\n")?;
+    let pixels: Vec<u8> = frame.iter().flat_map(|pix| palette.color(*pix)).collect();
+    writer.write_all(&pixels)
+}
+
+///8 bits RGB PNG, the image data is stored in uncompressed deflate blocks
+pub fn write_png(writer: &mut impl Write, frame: &[u8; FRAME_SIZE], palette: &Palette) -> std::io::Result<()> {
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(SCREEN_WIDTH as u32).to_be_bytes());
+    header.extend_from_slice(&(SCREEN_HEIGHT as u32).to_be_bytes());
+    // bit depth, color type rgb, compression, filter, interlace
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    // every scanline starts with its filter type, 0 for none
+    let mut raw = Vec::with_capacity(SCREEN_HEIGHT * (1 + SCREEN_WIDTH * 3));
+    for row in frame.chunks_exact(SCREEN_WIDTH) {
+        raw.push(0);
+        raw.extend(row.iter().flat_map(|pix| palette.color(*pix)));
+    }
+
+    writer.write_all(&PNG_SIGNATURE)?;
+    write_chunk(writer, b"IHDR", &header)?;
+    write_chunk(writer, b"IDAT", &zlib_stored(&raw))?;
+    write_chunk(writer, b"IEND", &[])
+}
+
+fn write_chunk(writer: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> std::io::Result<()> {
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(kind)?;
+    writer.write_all(data)?;
+    let crc = crc32(kind.iter().chain(data));
+    writer.write_all(&crc.to_be_bytes())
+}
+
+///Zlib stream made of stored (not compressed) deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let blocks = data.len().div_ceil(STORED_BLOCK_SIZE).max(1);
+    let mut out = Vec::with_capacity(2 + data.len() + blocks * 5 + 4);
+    // deflate with a 32K window, no preset dictionary, check bits making the header a multiple of 31
+    out.extend_from_slice(&[0x78, 0x01]);
+
+    let mut chunks = data.chunks(STORED_BLOCK_SIZE).peekable();
+    if chunks.peek().is_none() {
+        out.extend_from_slice(&[1, 0x00, 0x00, 0xFF, 0xFF]);
+    }
+    while let Some(chunk) = chunks.next() {
+        let last = chunks.peek().is_none();
+        let len = chunk.len() as u16;
+        out.push(last as u8);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(chunk);
+    }
+
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+fn crc32<'a>(bytes: impl IntoIterator<Item = &'a u8>) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    const MOD_ADLER: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in bytes {
+        a = (a + *byte as u32) % MOD_ADLER;
+        b = (b + a) % MOD_ADLER;
+    }
+    (b << 16) | a
+}
+
+//MARK: PNG decoding
+
+///Decode a 160x144 PNG, each pixel becomes the `PIX_*` shade closest to its gray level
+pub fn read_png_frame(bytes: &[u8]) -> Result<Box<[u8; FRAME_SIZE]>, ImageError> {
+    if bytes.get(..8) != Some(&PNG_SIGNATURE[..]) {
+        return Err(ImageError::NotPng);
+    }
+
+    let mut header = None;
+    let mut palette: &[u8] = &[];
+    let mut compressed = vec![];
+    let mut chunks = &bytes[8..];
+    while chunks.len() >= 12 {
+        let len = u32::from_be_bytes([chunks[0], chunks[1], chunks[2], chunks[3]]) as usize;
+        let data = chunks.get(8..8 + len).ok_or(ImageError::Corrupt("truncated chunk"))?;
+        match &chunks[4..8] {
+            b"IHDR" if len == 13 => header = Some(data),
+            b"PLTE" => palette = data,
+            b"IDAT" => compressed.extend_from_slice(data),
+            b"IEND" => break,
+            _ => (),
+        }
+        chunks = chunks.get(12 + len..).ok_or(ImageError::Corrupt("truncated chunk"))?;
+    }
+
+    let header = header.ok_or(ImageError::Corrupt("missing IHDR"))?;
+    let width = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+    let height = u32::from_be_bytes([header[4], header[5], header[6], header[7]]) as usize;
+    let (depth, color_type, interlace) = (header[8], header[9], header[12]);
+    if (width, height) != (SCREEN_WIDTH, SCREEN_HEIGHT) {
+        return Err(ImageError::WrongSize { width, height });
+    }
+    if interlace != 0 {
+        return Err(ImageError::Unsupported(String::from("interlaced images")));
+    }
+
+    let channels = match (color_type, depth) {
+        (0 | 3, 1 | 2 | 4 | 8) => 1,
+        (4, 8) => 2,
+        (2, 8) => 3,
+        (6, 8) => 4,
+        _ => return Err(ImageError::Unsupported(format!("color type {color_type} with {depth} bits"))),
+    };
+    let row_len = (width * channels * depth as usize).div_ceil(8);
+    let pixel_len = (channels * depth as usize).div_ceil(8);
+
+    let raw = zlib_inflate(&compressed)?;
+    if raw.len() < height * (1 + row_len) {
+        return Err(ImageError::Corrupt("not enough image data"));
+    }
+
+    let mut frame = Box::new([PIX_WHITE; FRAME_SIZE]);
+    let mut previous = vec![0; row_len];
+    for (y, line) in raw.chunks_exact(1 + row_len).take(height).enumerate() {
+        let mut row = line[1..].to_vec();
+        unfilter(line[0], &mut row, &previous, pixel_len)?;
+
+        for x in 0..width {
+            let gray = match color_type {
+                0 | 3 => {
+                    let shift = 8 - depth as usize - (x * depth as usize) % 8;
+                    let sample = (row[x * depth as usize / 8] >> shift) as usize & ((1 << depth) - 1);
+                    if color_type == 0 {
+                        sample * 255 / ((1 << depth) - 1)
+                    } else {
+                        let color = palette
+                            .get(sample * 3..sample * 3 + 3)
+                            .ok_or(ImageError::Corrupt("bad palette index"))?;
+                        color.iter().map(|c| *c as usize).sum::<usize>() / 3
+                    }
+                }
+                4 => row[x * 2] as usize,
+                _ => row[x * channels..x * channels + 3].iter().map(|c| *c as usize).sum::<usize>() / 3,
+            };
+            frame[y * SCREEN_WIDTH + x] = match (gray + 42) / 85 {
+                0 => PIX_BLACK,
+                1 => PIX_DARK_GRAY,
+                2 => PIX_LIGHT_GRAY,
+                _ => PIX_WHITE,
+            };
+        }
+        previous = row;
+    }
+
+    Ok(frame)
+}
+
+///Undo the filter of a scanline, `previous` is the already unfiltered line above
+fn unfilter(filter: u8, row: &mut [u8], previous: &[u8], pixel_len: usize) -> Result<(), ImageError> {
+    for i in 0..row.len() {
+        let left = if i >= pixel_len { row[i - pixel_len] } else { 0 };
+        let up = previous[i];
+        let up_left = if i >= pixel_len { previous[i - pixel_len] } else { 0 };
+        let predicted = match filter {
+            0 => 0,
+            1 => left,
+            2 => up,
+            3 => ((left as u16 + up as u16) / 2) as u8,
+            4 => {
+                let estimate = left as i16 + up as i16 - up_left as i16;
+                let distance = |value: u8| (estimate - value as i16).abs();
+                let (dl, du, dul) = (distance(left), distance(up), distance(up_left));
+                if dl <= du && dl <= dul {
+                    left
+                } else if du <= dul {
+                    up
+                } else {
+                    up_left
+                }
+            }
+            _ => return Err(ImageError::Corrupt("unknown filter type")),
+        };
+        row[i] = row[i].wrapping_add(predicted);
+    }
+    Ok(())
+}
+
+///Bits of a deflate stream, least significant first
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    /// Position in bits
+    pos: usize,
+}
+
+impl BitReader<'_> {
+    fn bits(&mut self, count: u8) -> Result<u32, ImageError> {
+        let mut value = 0;
+        for i in 0..count {
+            let byte = self.bytes.get(self.pos / 8).ok_or(ImageError::Corrupt("truncated deflate stream"))?;
+            value |= ((*byte >> (self.pos % 8)) as u32 & 1) << i;
+            self.pos += 1;
+        }
+        Ok(value)
+    }
+
+    fn align_to_byte(&mut self) {
+        self.pos = self.pos.div_ceil(8) * 8;
+    }
+}
+
+///Canonical Huffman code, decoded one bit at a time
+struct Huffman {
+    /// Number of codes of each length
+    counts: [u16; 16],
+    /// Symbols ordered by code
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0u16; 16];
+        for len in lengths {
+            counts[*len as usize] += 1;
+        }
+        counts[0] = 0;
+
+        let mut offsets = [0u16; 16];
+        for len in 1..15 {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, len) in lengths.iter().enumerate().filter(|(_, len)| **len != 0) {
+            symbols[offsets[*len as usize] as usize] = symbol as u16;
+            offsets[*len as usize] += 1;
+        }
+
+        Self { counts, symbols }
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u16, ImageError> {
+        // codes of a length come right after the codes one bit shorter, shifted left
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for count in &self.counts[1..] {
+            code |= reader.bits(1)? as i32;
+            let count = *count as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(ImageError::Corrupt("invalid Huffman code"))
+    }
+}
+
+///Decompress a whole zlib stream
+fn zlib_inflate(data: &[u8]) -> Result<Vec<u8>, ImageError> {
+    let (cmf, flg) = match data {
+        [cmf, flg, ..] => (*cmf, *flg),
+        _ => return Err(ImageError::Corrupt("truncated zlib header")),
+    };
+    if cmf & 0x0F != 8 || !(cmf as u16 * 256 + flg as u16).is_multiple_of(31) || flg & 0x20 != 0 {
+        return Err(ImageError::Corrupt("bad zlib header"));
+    }
+
+    let mut reader = BitReader { bytes: &data[2..], pos: 0 };
+    let mut out = vec![];
+    loop {
+        let last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => {
+                reader.align_to_byte();
+                let len = reader.bits(16)? as usize;
+                let start = reader.pos / 8 + 2;
+                let block = reader.bytes.get(start..start + len).ok_or(ImageError::Corrupt("truncated stored block"))?;
+                out.extend_from_slice(block);
+                reader.pos = (start + len) * 8;
+            }
+            1 => {
+                let mut lengths = [8; 288];
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                inflate_block(&mut reader, &mut out, &Huffman::new(&lengths), &Huffman::new(&[5; 30]))?;
+            }
+            2 => {
+                let (literals, distances) = read_dynamic_codes(&mut reader)?;
+                inflate_block(&mut reader, &mut out, &literals, &distances)?;
+            }
+            _ => return Err(ImageError::Corrupt("reserved deflate block type")),
+        }
+        if last {
+            break;
+        }
+    }
+
+    reader.align_to_byte();
+    let checksum = reader.bytes.get(reader.pos / 8..reader.pos / 8 + 4).ok_or(ImageError::Corrupt("missing adler32"))?;
+    if u32::from_be_bytes([checksum[0], checksum[1], checksum[2], checksum[3]]) != adler32(&out) {
+        return Err(ImageError::Corrupt("adler32 mismatch"));
+    }
+    Ok(out)
+}
+
+fn read_dynamic_codes(reader: &mut BitReader) -> Result<(Huffman, Huffman), ImageError> {
+    let literal_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_length_count = reader.bits(4)? as usize + 4;
+
+    let mut code_lengths = [0; 19];
+    for symbol in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_lengths[*symbol] = reader.bits(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_lengths);
+
+    let mut lengths = Vec::with_capacity(literal_count + distance_count);
+    while lengths.len() < literal_count + distance_count {
+        let (len, repeat) = match code_lengths.decode(reader)? {
+            len @ 0..=15 => (len as u8, 1),
+            16 => (*lengths.last().ok_or(ImageError::Corrupt("repeat without a length"))?, 3 + reader.bits(2)?),
+            17 => (0, 3 + reader.bits(3)?),
+            _ => (0, 11 + reader.bits(7)?),
+        };
+        lengths.extend(std::iter::repeat_n(len, repeat as usize));
+    }
+    if lengths.len() != literal_count + distance_count {
+        return Err(ImageError::Corrupt("code lengths overflow"));
+    }
+
+    Ok((Huffman::new(&lengths[..literal_count]), Huffman::new(&lengths[literal_count..])))
+}
+
+fn inflate_block(
+    reader: &mut BitReader,
+    out: &mut Vec<u8>,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> Result<(), ImageError> {
+    loop {
+        let symbol = literals.decode(reader)? as usize;
+        match symbol {
+            0..=255 => out.push(symbol as u8),
+            256 => return Ok(()),
+            _ => {
+                let code = symbol - 257;
+                let base = *LENGTH_BASE.get(code).ok_or(ImageError::Corrupt("bad length code"))?;
+                let len = base as usize + reader.bits(LENGTH_EXTRA[code])? as usize;
+
+                let code = distances.decode(reader)? as usize;
+                let base = *DISTANCE_BASE.get(code).ok_or(ImageError::Corrupt("bad distance code"))?;
+                let distance = base as usize + reader.bits(DISTANCE_EXTRA[code])? as usize;
+                if distance > out.len() {
+                    return Err(ImageError::Corrupt("distance before the start of the stream"));
+                }
+
+                // the copy may overlap what it produces
+                let start = out.len() - distance;
+                for i in 0..len {
+                    out.push(out[start + i]);
+                }
+            }
+        }
+    }
+}
+
+//MARK: Errors
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PaletteError {
+    InvalidColor(String),
+    WrongCount(usize),
+}
+
+impl Display for PaletteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidColor(color) => write!(f, "invalid color \"{color}\", expected 6 hex digits"),
+            Self::WrongCount(count) => write!(f, "a palette needs 4 colors, got {count}"),
+        }
+    }
+}
+
+impl Error for PaletteError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImageError {
+    NotPng,
+    Unsupported(String),
+    Corrupt(&'static str),
+    WrongSize { width: usize, height: usize },
+}
+
+impl Display for ImageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotPng => write!(f, "not a PNG file"),
+            Self::Unsupported(what) => write!(f, "unsupported PNG : {what}"),
+            Self::Corrupt(what) => write!(f, "corrupt PNG : {what}"),
+            Self::WrongSize { width, height } => {
+                write!(f, "the image is {width}x{height}, expected {SCREEN_WIDTH}x{SCREEN_HEIGHT}")
+            }
+        }
+    }
+}
+
+impl Error for ImageError {}
+
+//MARK: TEST
+
+#[cfg(test)]
+mod test {
+    use crate::graphics::{
+        FRAME_SIZE, PIX_BLACK, PIX_DARK_GRAY, PIX_LIGHT_GRAY, PIX_WHITE, SCREEN_HEIGHT, SCREEN_WIDTH,
+        image::{
+            ImageError, Palette, PaletteError, adler32, crc32, read_png_frame, unfilter, write_png, write_ppm,
+            zlib_inflate, zlib_stored,
+        },
+    };
+
+    #[test]
+    pub fn test_checksums() {
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+    }
+
+    #[test]
+    pub fn test_palette_parse() {
+        let palette = Palette::parse("e0f8d0,88c070,#346856,081820").unwrap();
+        assert_eq!(palette.color(PIX_WHITE), [0xE0, 0xF8, 0xD0]);
+        assert_eq!(palette.color(PIX_BLACK), [0x08, 0x18, 0x20]);
+
+        assert_eq!(Palette::parse("ffffff,000000"), Err(PaletteError::WrongCount(2)));
+        assert!(matches!(Palette::parse("fff,0,0,0"), Err(PaletteError::InvalidColor(_))));
+    }
+
+    #[test]
+    pub fn test_zlib_stored_blocks() {
+        let data = vec![0x42; 0x1_0000 + 10];
+        let stream = zlib_stored(&data);
+
+        // two blocks, only the second one is final
+        assert_eq!(&stream[2..7], &[0, 0xFF, 0xFF, 0x00, 0x00]);
+        let second = 7 + 0xFFFF;
+        assert_eq!(&stream[second..second + 5], &[1, 11, 0, !11, 0xFF]);
+        assert_eq!(stream.len(), 2 + 5 * 2 + data.len() + 4);
+    }
+
+    #[test]
+    pub fn test_ppm() {
+        let mut frame = [PIX_WHITE; FRAME_SIZE];
+        frame[1] = PIX_BLACK;
+        let mut out = vec![];
+        write_ppm(&mut out, &frame, &Palette::default()).unwrap();
+
+        let header = b"P6\n160 144\n255\n";
+        assert_eq!(&out[..header.len()], header);
+        assert_eq!(&out[header.len()..header.len() + 6], &[0xFF, 0xFF, 0xFF, 0, 0, 0]);
+        assert_eq!(out.len(), header.len() + FRAME_SIZE * 3);
+    }
+
+    #[test]
+    pub fn test_png_layout() {
+        let frame = [PIX_BLACK; FRAME_SIZE];
+        let mut out = vec![];
+        write_png(&mut out, &frame, &Palette::default()).unwrap();
+
+        assert_eq!(&out[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&out[12..16], b"IHDR");
+        assert_eq!(&out[16..20], &(SCREEN_WIDTH as u32).to_be_bytes());
+        assert_eq!(&out[20..24], &(SCREEN_HEIGHT as u32).to_be_bytes());
+        assert_eq!(&out[out.len() - 12..], &[0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xAE, 0x42, 0x60, 0x82]);
+
+        let raw_len = SCREEN_HEIGHT * (1 + SCREEN_WIDTH * 3);
+        let idat_len = u32::from_be_bytes(out[33..37].try_into().unwrap()) as usize;
+        assert_eq!(&out[37..41], b"IDAT");
+        assert_eq!(idat_len, 2 + raw_len.div_ceil(0xFFFF) * 5 + raw_len + 4);
+    }
+
+    #[test]
+    pub fn test_inflate() {
+        // fixed Huffman codes, with overlapping copies
+        let fixed = [120, 218, 203, 72, 205, 201, 201, 87, 200, 64, 39, 1, 104, 3, 8, 177];
+        assert_eq!(zlib_inflate(&fixed).unwrap(), b"hello hello hello hello");
+
+        let dynamic = [
+            0x78, 0xDA, 0x25, 0x8A, 0x89, 0x09, 0x00, 0x30, 0x10, 0xC2, 0x66, 0x8D, 0xBA, 0xFF, 0x0C, 0xCD, 0x51, 0x1F,
+            0x10, 0xB5, 0xD0, 0x68, 0x5A, 0x48, 0x36, 0xD2, 0x99, 0x2C, 0x27, 0x3F, 0xE2, 0xE7, 0xE6, 0x9A, 0xA2, 0x1C,
+            0xEE, 0xF9, 0x00, 0xBC, 0xBA, 0x16, 0xF7,
+        ];
+        assert_eq!(
+            zlib_inflate(&dynamic).unwrap(),
+            b"caacbaacaccaabbddabcdaabcbadadaaaaaaabacbcaabcababbabadabdda"
+        );
+
+        let data = vec![0x42; 0x1_0000 + 10];
+        assert_eq!(zlib_inflate(&zlib_stored(&data)).unwrap(), data);
+
+        let mut corrupt = fixed;
+        corrupt[15] ^= 1;
+        assert_eq!(zlib_inflate(&corrupt), Err(ImageError::Corrupt("adler32 mismatch")));
+    }
+
+    #[test]
+    pub fn test_unfilter() {
+        let previous = [10, 20, 30, 40];
+        let mut row = [1, 2, 3, 4];
+        unfilter(1, &mut row, &previous, 2).unwrap();
+        assert_eq!(row, [1, 2, 4, 6]);
+
+        let mut row = [1, 2, 3, 4];
+        unfilter(3, &mut row, &previous, 2).unwrap();
+        assert_eq!(row, [6, 12, 21, 30]);
+
+        // paeth picks up for the first pixel, then the closest of left, up and up left
+        let mut row = [1, 2, 3, 4];
+        unfilter(4, &mut row, &previous, 2).unwrap();
+        assert_eq!(row, [11, 22, 33, 44]);
+    }
+
+    #[test]
+    pub fn test_png_round_trip() {
+        let mut frame = [PIX_WHITE; FRAME_SIZE];
+        frame[0] = PIX_BLACK;
+        frame[1] = PIX_DARK_GRAY;
+        frame[FRAME_SIZE - 1] = PIX_LIGHT_GRAY;
+        let mut out = vec![];
+        write_png(&mut out, &frame, &Palette::default()).unwrap();
+
+        assert_eq!(*read_png_frame(&out).unwrap(), frame);
+        assert_eq!(read_png_frame(b"P6\n160 144\n255\n"), Err(ImageError::NotPng));
+    }
+}
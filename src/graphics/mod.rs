@@ -1,6 +1,21 @@
-
+pub mod image;
+pub mod ppu;
 
 pub const PIX_WHITE      :u8 = 0b11;
 pub const PIX_DARK_GRAY  :u8 = 0b10;    
 pub const PIX_LIGHT_GRAY :u8 = 0b01;        
 pub const PIX_BLACK      :u8 = 0b00;
+
+pub const SCREEN_WIDTH  :usize = 160;
+pub const SCREEN_HEIGHT :usize = 144;
+pub const FRAME_SIZE    :usize = SCREEN_WIDTH * SCREEN_HEIGHT;
+
+///Convert a 2 bits color index to a `PIX_*` value through a BGP/OBP palette
+pub const fn shade(palette: u8, color: u8) -> u8 {
+    match (palette >> (color * 2)) & 0b11 {
+        0 => PIX_WHITE,
+        1 => PIX_LIGHT_GRAY,
+        2 => PIX_DARK_GRAY,
+        _ => PIX_BLACK,
+    }
+}
@@ -0,0 +1,348 @@
+use std::collections::VecDeque;
+
+use crate::graphics::{
+    PIX_WHITE, SCREEN_WIDTH, shade,
+    ppu::{
+        LCDC_BG_ENABLE, LCDC_BG_MAP, LCDC_OBJ_ENABLE, LCDC_WINDOW_ENABLE, LCDC_WINDOW_MAP,
+        MAX_SPRITES_PER_LINE, Ppu, Sprite,
+    },
+};
+
+const BG_MAP_LOW: usize = 0x1800;
+const BG_MAP_HIGH: usize = 0x1C00;
+
+/// Dots spent on the discarded first tile fetch of every line
+const WARMUP_DOTS: u8 = 6;
+const SPRITE_FETCH_DOTS: u8 = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum FetchStep {
+    #[default]
+    Tile,
+    DataLow,
+    DataHigh,
+    Push,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct ObjPixel {
+    color: u8,
+    attributes: u8,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SpriteFetch {
+    sprite: usize,
+    dots_left: u8,
+}
+
+///Background fetcher, background and sprite FIFOs of the line being drawn
+#[derive(Debug, Default)]
+pub(super) struct PixelFifo {
+    bg: VecDeque<u8>,
+    obj: VecDeque<ObjPixel>,
+
+    step: FetchStep,
+    /// Fetcher steps take two dots, set on the first one
+    first_dot_done: bool,
+    stall: u8,
+    /// Tile column fetched next
+    fetch_x: u8,
+    tile: u8,
+    low: u8,
+    high: u8,
+    /// The fetcher switched to the window on this line
+    window: bool,
+
+    /// Pixels already pushed to the LCD
+    x: u8,
+    /// Pixels still to drop because of SCX fine scroll, or WX < 7
+    discard: u8,
+
+    fetched_sprites: [bool; MAX_SPRITES_PER_LINE],
+    sprite_fetch: Option<SpriteFetch>,
+}
+
+impl Ppu {
+    pub(super) fn start_fifo_line(&mut self) {
+        self.fifo = PixelFifo {
+            stall: WARMUP_DOTS,
+            discard: self.scx % 8,
+            ..Default::default()
+        };
+    }
+
+    ///Run one dot of mode 3, returns true once the 160 pixels of the line are out
+    pub(super) fn fifo_dot(&mut self) -> bool {
+        if let Some(fetch) = &mut self.fifo.sprite_fetch {
+            fetch.dots_left -= 1;
+            if fetch.dots_left == 0 {
+                let sprite = fetch.sprite;
+                self.fifo.sprite_fetch = None;
+                self.fifo.fetched_sprites[sprite] = true;
+                self.merge_sprite(sprite);
+            }
+            return false;
+        }
+
+        if self.fifo.stall > 0 {
+            self.fifo.stall -= 1;
+            return false;
+        }
+
+        self.check_window_start();
+
+        if let Some(sprite) = self.next_sprite() {
+            // the background fetch in progress has to reach its high byte before the sprite is fetched,
+            // so a sprite costs 6 dots plus the pixels of its tile right of it minus 2
+            if matches!(self.fifo.step, FetchStep::DataHigh | FetchStep::Push) && !self.fifo.bg.is_empty() {
+                self.fifo.sprite_fetch = Some(SpriteFetch { sprite, dots_left: SPRITE_FETCH_DOTS - 1 });
+            } else {
+                self.fetch_dot();
+            }
+            return false;
+        }
+
+        self.fetch_dot();
+        self.shift_pixel()
+    }
+
+    fn window_visible(&self) -> bool {
+        self.lcdc & LCDC_WINDOW_ENABLE != 0 && self.window_triggered && self.wx <= 166
+    }
+
+    ///Restart the fetcher on the window when the current pixel reaches WX
+    fn check_window_start(&mut self) {
+        if self.fifo.window || !self.window_visible() || self.fifo.x as usize + 7 < self.wx as usize {
+            return;
+        }
+
+        self.fifo.window = true;
+        self.fifo.bg.clear();
+        self.fifo.step = FetchStep::Tile;
+        self.fifo.first_dot_done = false;
+        self.fifo.fetch_x = 0;
+        if self.wx < 7 {
+            self.fifo.discard = 7 - self.wx;
+        }
+    }
+
+    ///First sprite of the line reaching the current pixel and not fetched yet
+    fn next_sprite(&self) -> Option<usize> {
+        if self.lcdc & LCDC_OBJ_ENABLE == 0 {
+            return None;
+        }
+        self.line_sprites
+            .iter()
+            .enumerate()
+            .find(|(i, sprite)| !self.fifo.fetched_sprites[*i] && sprite.x as usize <= self.fifo.x as usize + 8)
+            .map(|(i, _)| i)
+    }
+
+    fn fetch_dot(&mut self) {
+        if self.fifo.step == FetchStep::Push {
+            if self.fifo.bg.is_empty() {
+                self.push_tile();
+            }
+            return;
+        }
+
+        if !self.fifo.first_dot_done {
+            self.fifo.first_dot_done = true;
+            return;
+        }
+        self.fifo.first_dot_done = false;
+
+        match self.fifo.step {
+            FetchStep::Tile => {
+                self.fifo.tile = self.fetch_tile_number();
+                self.fifo.step = FetchStep::DataLow;
+            }
+            FetchStep::DataLow => {
+                self.fifo.low = self.vram[self.fetch_data_addr()];
+                self.fifo.step = FetchStep::DataHigh;
+            }
+            FetchStep::DataHigh => {
+                self.fifo.high = self.vram[self.fetch_data_addr() + 1];
+                self.fifo.step = FetchStep::Push;
+            }
+            FetchStep::Push => unreachable!(),
+        }
+    }
+
+    fn fetch_tile_number(&self) -> u8 {
+        let (map, x, y) = if self.fifo.window {
+            let map = if self.lcdc & LCDC_WINDOW_MAP != 0 { BG_MAP_HIGH } else { BG_MAP_LOW };
+            (map, self.fifo.fetch_x as usize, self.window_line as usize / 8)
+        } else {
+            let map = if self.lcdc & LCDC_BG_MAP != 0 { BG_MAP_HIGH } else { BG_MAP_LOW };
+            let x = (self.scx as usize / 8 + self.fifo.fetch_x as usize) & 31;
+            (map, x, ((self.ly as usize + self.scy as usize) & 0xFF) / 8)
+        };
+        self.vram[map + y * 32 + x]
+    }
+
+    fn fetch_data_addr(&self) -> usize {
+        let row = if self.fifo.window {
+            self.window_line as usize % 8
+        } else {
+            (self.ly as usize + self.scy as usize) % 8
+        };
+        self.bg_tile_addr(self.fifo.tile) + row * 2
+    }
+
+    fn push_tile(&mut self) {
+        for bit in (0..8).rev() {
+            let color = (((self.fifo.high >> bit) & 1) << 1) | ((self.fifo.low >> bit) & 1);
+            self.fifo.bg.push_back(color);
+        }
+        self.fifo.fetch_x += 1;
+        self.fifo.step = FetchStep::Tile;
+    }
+
+    ///Mix the sprite into the sprite FIFO, pixels already there keep priority
+    fn merge_sprite(&mut self, index: usize) {
+        let sprite = self.line_sprites[index];
+        let x = self.fifo.x as usize;
+        while self.fifo.obj.len() < 8 {
+            self.fifo.obj.push_back(ObjPixel::default());
+        }
+
+        for column in 0..8 {
+            let Some(slot) = (sprite.x as usize + column).checked_sub(8 + x) else {
+                continue; // clipped by the left border
+            };
+            if self.fifo.obj[slot].color == 0 {
+                let color = self.sprite_pixel(&sprite, x + slot).unwrap_or(0);
+                self.fifo.obj[slot] = ObjPixel { color, attributes: sprite.attributes };
+            }
+        }
+    }
+
+    ///Pop a pixel to the LCD, returns true when the line is complete
+    fn shift_pixel(&mut self) -> bool {
+        let Some(bg_color) = self.fifo.bg.pop_front() else {
+            return false;
+        };
+        if self.fifo.discard > 0 {
+            self.fifo.discard -= 1;
+            return false;
+        }
+        let obj = self.fifo.obj.pop_front().unwrap_or_default();
+
+        let bg_color = if self.lcdc & LCDC_BG_ENABLE != 0 { bg_color } else { 0 };
+        let obj_visible = self.lcdc & LCDC_OBJ_ENABLE != 0
+            && obj.color != 0
+            && (obj.attributes & Sprite::BG_PRIORITY == 0 || bg_color == 0);
+
+        let pixel = if obj_visible {
+            let palette = if obj.attributes & Sprite::PALETTE != 0 { self.obp1 } else { self.obp0 };
+            shade(palette, obj.color)
+        } else if self.lcdc & LCDC_BG_ENABLE != 0 {
+            shade(self.bgp, bg_color)
+        } else {
+            PIX_WHITE
+        };
+
+        let x = self.fifo.x as usize;
+        self.framebuffer[self.ly as usize * SCREEN_WIDTH + x] = pixel;
+        self.fifo.x += 1;
+
+        if self.fifo.x as usize == SCREEN_WIDTH {
+            if self.fifo.window {
+                self.window_line += 1;
+            }
+            return true;
+        }
+        false
+    }
+}
+
+//MARK: TEST
+
+#[cfg(test)]
+mod test {
+    use crate::graphics::ppu::{
+        BGP_ADDR, LCDC_ADDR, Mode, OBP0_ADDR, Ppu, Renderer, SCX_ADDR, SCY_ADDR, WX_ADDR, WY_ADDR,
+    };
+
+    ///Dots from the start of the line to the end of mode 3
+    fn mode3_end(ppu: &mut Ppu) -> usize {
+        let mut dots = 0;
+        while ppu.mode() != Mode::Drawing {
+            ppu.tick_dot();
+        }
+        dots += 80;
+        while ppu.mode() == Mode::Drawing {
+            ppu.tick_dot();
+            dots += 1;
+        }
+        dots
+    }
+
+    fn enabled_ppu(scx: u8) -> Ppu {
+        let mut ppu = Ppu::new(Renderer::Fifo);
+        ppu.writeb(SCX_ADDR, scx);
+        ppu.writeb(LCDC_ADDR, 0x91);
+        ppu
+    }
+
+    #[test]
+    pub fn test_mode3_length() {
+        assert_eq!(mode3_end(&mut enabled_ppu(0)), 80 + 172);
+        assert_eq!(mode3_end(&mut enabled_ppu(3)), 80 + 175);
+        assert_eq!(mode3_end(&mut enabled_ppu(8)), 80 + 172);
+    }
+
+    #[test]
+    pub fn test_window_penalty() {
+        let mut ppu = enabled_ppu(0);
+        ppu.writeb(WX_ADDR, 7 + 80);
+        ppu.writeb(LCDC_ADDR, 0xB1);
+        assert_eq!(mode3_end(&mut ppu), 80 + 172 + 6);
+    }
+
+    #[test]
+    pub fn test_sprite_penalty() {
+        // screen x of the sprite, dots added to mode 3
+        for (x, penalty) in [(80, 11), (82, 9), (85, 6), (87, 6)] {
+            let mut ppu = enabled_ppu(0);
+            for (i, byte) in [16, 8 + x, 0, 0].into_iter().enumerate() {
+                ppu.write_oam(0xFE00 + i as u16, byte);
+            }
+            ppu.writeb(LCDC_ADDR, 0x93);
+            assert_eq!(mode3_end(&mut ppu), 80 + 172 + penalty, "sprite at {x}");
+        }
+    }
+
+    ///Both renderers must agree on frames that do not change mid line
+    #[test]
+    pub fn test_matches_scanline() {
+        let mut scanline = Ppu::new(Renderer::Scanline);
+        let mut fifo = Ppu::new(Renderer::Fifo);
+
+        for ppu in [&mut scanline, &mut fifo] {
+            for addr in 0..0x800u16 {
+                ppu.write_vram(0x8000 + addr, (addr as u8).wrapping_mul(37) ^ (addr >> 4) as u8);
+            }
+            for addr in 0..0x800u16 {
+                ppu.write_vram(0x9800 + addr, (addr as u8).wrapping_mul(13));
+            }
+            for (i, byte) in (0..0xA0u16).map(|i| (i as u8).wrapping_mul(71)).enumerate() {
+                ppu.write_oam(0xFE00 + i as u16, byte);
+            }
+            ppu.writeb(BGP_ADDR, 0b00_01_10_11);
+            ppu.writeb(OBP0_ADDR, 0b11_10_01_00);
+            ppu.writeb(SCX_ADDR, 13);
+            ppu.writeb(SCY_ADDR, 7);
+            ppu.writeb(WY_ADDR, 50);
+            ppu.writeb(WX_ADDR, 60);
+            ppu.writeb(LCDC_ADDR, 0xF3);
+            for _ in 0..114 * 154 {
+                ppu.tick();
+            }
+        }
+
+        assert!(scanline.framebuffer() == fifo.framebuffer());
+    }
+}
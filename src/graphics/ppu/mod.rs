@@ -0,0 +1,525 @@
+use crate::{
+    cpu::interrupts::Interrupt,
+    graphics::{FRAME_SIZE, PIX_WHITE, ppu::fifo::PixelFifo},
+};
+
+mod fifo;
+mod scanline;
+
+pub const LCDC_ADDR: u16 = 0xFF40;
+pub const STAT_ADDR: u16 = 0xFF41;
+pub const SCY_ADDR: u16 = 0xFF42;
+pub const SCX_ADDR: u16 = 0xFF43;
+pub const LY_ADDR: u16 = 0xFF44;
+pub const LYC_ADDR: u16 = 0xFF45;
+pub const BGP_ADDR: u16 = 0xFF47;
+pub const OBP0_ADDR: u16 = 0xFF48;
+pub const OBP1_ADDR: u16 = 0xFF49;
+pub const WY_ADDR: u16 = 0xFF4A;
+pub const WX_ADDR: u16 = 0xFF4B;
+
+const VRAM_SIZE: usize = 0x2000;
+const OAM_SIZE: usize = 0xA0;
+
+const DOTS_PER_LINE: u16 = 456;
+const OAM_SCAN_DOTS: u16 = 80;
+const DRAWING_DOTS: u16 = 172;
+const VBLANK_LINE: u8 = 144;
+const LINES_PER_FRAME: u8 = 154;
+/// LY already reads 0 this many dots into line 153
+const LAST_LINE_LY_DOTS: u16 = 4;
+const MAX_SPRITES_PER_LINE: usize = 10;
+
+// LCDC bits
+const LCDC_BG_ENABLE: u8 = 1 << 0;
+const LCDC_OBJ_ENABLE: u8 = 1 << 1;
+const LCDC_OBJ_SIZE: u8 = 1 << 2;
+const LCDC_BG_MAP: u8 = 1 << 3;
+const LCDC_TILE_DATA: u8 = 1 << 4;
+const LCDC_WINDOW_ENABLE: u8 = 1 << 5;
+const LCDC_WINDOW_MAP: u8 = 1 << 6;
+const LCDC_LCD_ENABLE: u8 = 1 << 7;
+
+const STAT_WRITE_MASK: u8 = 0b0111_1000;
+const STAT_LYC_EQUAL: u8 = 1 << 2;
+const STAT_HBLANK_SOURCE: u8 = 1 << 3;
+const STAT_VBLANK_SOURCE: u8 = 1 << 4;
+const STAT_OAM_SOURCE: u8 = 1 << 5;
+const STAT_LYC_SOURCE: u8 = 1 << 6;
+
+///How the pixels of a line are produced
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Renderer {
+    ///Whole line drawn at the end of a fixed length mode 3
+    #[default]
+    Scanline,
+    ///Dot by dot fetcher and FIFOs, mode 3 length depends on scrolling, window and sprites
+    Fifo,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    HBlank = 0,
+    VBlank = 1,
+    OamScan = 2,
+    Drawing = 3,
+}
+
+///An OAM entry, with the position already converted to screen coordinates + (8, 16)
+#[derive(Debug, Clone, Copy)]
+struct Sprite {
+    y: u8,
+    x: u8,
+    tile: u8,
+    attributes: u8,
+}
+
+impl Sprite {
+    const BG_PRIORITY: u8 = 1 << 7;
+    const FLIP_Y: u8 = 1 << 6;
+    const FLIP_X: u8 = 1 << 5;
+    const PALETTE: u8 = 1 << 4;
+}
+
+///Pixel processing unit: VRAM, OAM, the LCD registers and the framebuffer
+#[derive(Debug)]
+pub struct Ppu {
+    renderer: Renderer,
+    vram: [u8; VRAM_SIZE], // 0x8000 -> 0x9FFF
+    oam: [u8; OAM_SIZE],   // 0xFE00 -> 0xFE9F
+
+    lcdc: u8,
+    stat: u8,
+    scy: u8,
+    scx: u8,
+    ly: u8,
+    lyc: u8,
+    bgp: u8,
+    obp0: u8,
+    obp1: u8,
+    wy: u8,
+    wx: u8,
+
+    mode: Mode,
+    /// Dot (T-cycle) inside the current line
+    dot: u16,
+    /// Set once LY matched WY during the frame
+    window_triggered: bool,
+    /// Line of the window to draw next, only advances on lines showing the window
+    window_line: u8,
+    line_sprites: Vec<Sprite>,
+    fifo: PixelFifo,
+
+    /// OR of the enabled STAT sources, the interrupt is only requested on its rising edge
+    stat_line: bool,
+    /// Interrupts raised since the last tick, as an IF mask
+    interrupts: u8,
+    /// Frames completed since power on
+    frames: u64,
+
+    framebuffer: Box<[u8; FRAME_SIZE]>,
+}
+
+impl Default for Ppu {
+    fn default() -> Self {
+        Self::new(Renderer::default())
+    }
+}
+
+impl Ppu {
+    pub fn new(renderer: Renderer) -> Self {
+        Self {
+            renderer,
+            vram: [0; VRAM_SIZE],
+            oam: [0; OAM_SIZE],
+            lcdc: 0,
+            stat: 0,
+            scy: 0,
+            scx: 0,
+            ly: 0,
+            lyc: 0,
+            bgp: 0,
+            obp0: 0,
+            obp1: 0,
+            wy: 0,
+            wx: 0,
+            mode: Mode::HBlank,
+            dot: 0,
+            window_triggered: false,
+            window_line: 0,
+            line_sprites: Vec::with_capacity(MAX_SPRITES_PER_LINE),
+            fifo: PixelFifo::default(),
+            stat_line: false,
+            interrupts: 0,
+            frames: 0,
+            framebuffer: Box::new([PIX_WHITE; FRAME_SIZE]),
+        }
+    }
+}
+
+impl Ppu {
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    ///Incremented each time the PPU enters VBlank with a complete frame
+    pub fn frame_count(&self) -> u64 {
+        self.frames
+    }
+
+    ///Last rendered frame, one `PIX_*` value per pixel, row by row
+    pub fn framebuffer(&self) -> &[u8; FRAME_SIZE] {
+        &self.framebuffer
+    }
+
+    const fn lcd_enabled(&self) -> bool {
+        self.lcdc & LCDC_LCD_ENABLE != 0
+    }
+}
+
+// -- timing --
+impl Ppu {
+    ///Advance by one M-cycle, returns the interrupts requested meanwhile as an IF mask
+    pub fn tick(&mut self) -> u8 {
+        for _ in 0..4 {
+            self.tick_dot();
+        }
+        std::mem::take(&mut self.interrupts)
+    }
+
+    fn tick_dot(&mut self) {
+        if !self.lcd_enabled() {
+            return;
+        }
+
+        self.dot += 1;
+        match (self.mode, self.renderer) {
+            (Mode::OamScan, _) if self.dot == OAM_SCAN_DOTS => {
+                self.scan_oam();
+                self.mode = Mode::Drawing;
+                if self.renderer == Renderer::Fifo {
+                    self.start_fifo_line();
+                }
+            }
+            (Mode::Drawing, Renderer::Scanline) if self.dot == OAM_SCAN_DOTS + DRAWING_DOTS => {
+                self.render_line();
+                self.mode = Mode::HBlank;
+            }
+            (Mode::Drawing, Renderer::Fifo) if self.fifo_dot() => self.mode = Mode::HBlank,
+            _ => (),
+        }
+
+        if self.dot == DOTS_PER_LINE {
+            self.dot = 0;
+            self.next_line();
+        } else if self.ly == LINES_PER_FRAME - 1 && self.dot == LAST_LINE_LY_DOTS {
+            self.ly = 0;
+        }
+
+        self.check_window_trigger();
+        self.update_stat_line();
+    }
+
+    ///WY is compared with LY all along the frame, once they matched the window stays enabled until the next frame
+    fn check_window_trigger(&mut self) {
+        if self.lcd_enabled() && self.ly == self.wy {
+            self.window_triggered = true;
+        }
+    }
+
+    ///Request STAT if one of the enabled sources rose while all of them were low.
+    ///The mode 2 source also fires when entering line 144, as if an OAM scan started there
+    fn update_stat_line(&mut self) {
+        let oam_scan = self.mode == Mode::OamScan || (self.ly == VBLANK_LINE && self.dot == 0);
+        let line = self.lcd_enabled()
+            && ((self.stat & STAT_HBLANK_SOURCE != 0 && self.mode == Mode::HBlank)
+                || (self.stat & STAT_VBLANK_SOURCE != 0 && self.mode == Mode::VBlank)
+                || (self.stat & STAT_OAM_SOURCE != 0 && oam_scan)
+                || (self.stat & STAT_LYC_SOURCE != 0 && self.ly == self.lyc));
+
+        if line && !self.stat_line {
+            self.interrupts |= Interrupt::Stat.mask();
+        }
+        self.stat_line = line;
+    }
+
+    fn next_line(&mut self) {
+        // LY was already reset during line 153
+        if self.mode == Mode::VBlank && self.ly == 0 {
+            self.window_triggered = false;
+            self.window_line = 0;
+        } else {
+            self.ly += 1;
+        }
+
+        self.mode = if self.ly >= VBLANK_LINE { Mode::VBlank } else { Mode::OamScan };
+        if self.ly == VBLANK_LINE {
+            self.interrupts |= Interrupt::VBlank.mask();
+            self.frames += 1;
+        }
+    }
+
+    ///Select the first 10 sprites overlapping the current line, in OAM order
+    fn scan_oam(&mut self) {
+        let height = if self.lcdc & LCDC_OBJ_SIZE != 0 { 16 } else { 8 };
+        let line = self.ly as u16 + 16;
+
+        self.line_sprites.clear();
+        for entry in self.oam.chunks_exact(4) {
+            let y = entry[0] as u16;
+            if line >= y && line < y + height {
+                self.line_sprites.push(Sprite { y: entry[0], x: entry[1], tile: entry[2], attributes: entry[3] });
+                if self.line_sprites.len() == MAX_SPRITES_PER_LINE {
+                    break;
+                }
+            }
+        }
+    }
+
+    fn set_lcdc(&mut self, byte: u8) {
+        let was_enabled = self.lcd_enabled();
+        self.lcdc = byte;
+
+        if was_enabled && !self.lcd_enabled() {
+            self.ly = 0;
+            self.dot = 0;
+            self.mode = Mode::HBlank;
+        } else if !was_enabled && self.lcd_enabled() {
+            self.mode = Mode::OamScan;
+            self.window_triggered = false;
+            self.window_line = 0;
+            self.check_window_trigger();
+        }
+    }
+}
+
+// -- memory --
+impl Ppu {
+    ///0x8000 -> 0x9FFF
+    pub fn read_vram(&self, addr: u16) -> u8 {
+        self.vram[(addr - 0x8000) as usize]
+    }
+
+    ///0x8000 -> 0x9FFF
+    pub fn write_vram(&mut self, addr: u16, byte: u8) {
+        self.vram[(addr - 0x8000) as usize] = byte;
+    }
+
+    ///0xFE00 -> 0xFE9F
+    pub fn read_oam(&self, addr: u16) -> u8 {
+        self.oam[(addr - 0xFE00) as usize]
+    }
+
+    ///0xFE00 -> 0xFE9F
+    pub fn write_oam(&mut self, addr: u16, byte: u8) {
+        self.oam[(addr - 0xFE00) as usize] = byte;
+    }
+
+    ///0xFF40 -> 0xFF4B, except 0xFF46
+    pub fn readb(&self, addr: u16) -> u8 {
+        match addr {
+            LCDC_ADDR => self.lcdc,
+            STAT_ADDR => {
+                let mode = if self.lcd_enabled() { self.mode as u8 } else { 0 };
+                let lyc_equal = if self.lcd_enabled() && self.ly == self.lyc { STAT_LYC_EQUAL } else { 0 };
+                0x80 | self.stat | lyc_equal | mode
+            }
+            SCY_ADDR => self.scy,
+            SCX_ADDR => self.scx,
+            LY_ADDR => self.ly,
+            LYC_ADDR => self.lyc,
+            BGP_ADDR => self.bgp,
+            OBP0_ADDR => self.obp0,
+            OBP1_ADDR => self.obp1,
+            WY_ADDR => self.wy,
+            WX_ADDR => self.wx,
+            _ => 0xFF,
+        }
+    }
+
+    ///0xFF40 -> 0xFF4B, except 0xFF46
+    pub fn writeb(&mut self, addr: u16, byte: u8) {
+        match addr {
+            LCDC_ADDR => {
+                self.set_lcdc(byte);
+                self.update_stat_line();
+            }
+            STAT_ADDR => {
+                self.stat = byte & STAT_WRITE_MASK;
+                self.update_stat_line();
+            }
+            SCY_ADDR => self.scy = byte,
+            SCX_ADDR => self.scx = byte,
+            LY_ADDR => (), // read only
+            LYC_ADDR => {
+                self.lyc = byte;
+                self.update_stat_line();
+            }
+            BGP_ADDR => self.bgp = byte,
+            OBP0_ADDR => self.obp0 = byte,
+            OBP1_ADDR => self.obp1 = byte,
+            WY_ADDR => {
+                self.wy = byte;
+                self.check_window_trigger();
+            }
+            WX_ADDR => self.wx = byte,
+            _ => (),
+        }
+    }
+}
+
+//MARK: TEST
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        cpu::interrupts::Interrupt,
+        graphics::ppu::{
+            LCDC_ADDR, LY_ADDR, LYC_ADDR, Mode, Ppu, STAT_ADDR, STAT_HBLANK_SOURCE, STAT_LYC_SOURCE,
+            STAT_OAM_SOURCE, STAT_VBLANK_SOURCE,
+        },
+    };
+
+    ///M-cycles per line
+    const LINE: usize = 114;
+
+    fn tick_n(ppu: &mut Ppu, n: usize) {
+        (0..n).for_each(|_| {
+            ppu.tick();
+        });
+    }
+
+    ///Number of M-cycles, out of the next n, requesting the interrupt
+    fn count_interrupts(ppu: &mut Ppu, n: usize, interrupt: Interrupt) -> usize {
+        (0..n).filter(|_| ppu.tick() & interrupt.mask() != 0).count()
+    }
+
+    ///STAT interrupts during the first frame after turning the lcd on, before line 0 comes back
+    fn stat_per_frame(sources: u8, lyc: u8) -> usize {
+        let mut ppu = Ppu::default();
+        ppu.writeb(STAT_ADDR, sources);
+        ppu.writeb(LYC_ADDR, lyc);
+        ppu.writeb(LCDC_ADDR, 0x80);
+        count_interrupts(&mut ppu, LINE * 154 - 1, Interrupt::Stat)
+    }
+
+    #[test]
+    pub fn test_mode_sequence() {
+        let mut ppu = Ppu::default();
+        ppu.writeb(LCDC_ADDR, 0x80);
+        assert_eq!(ppu.mode(), Mode::OamScan);
+
+        tick_n(&mut ppu, 20);
+        assert_eq!(ppu.mode(), Mode::Drawing);
+        tick_n(&mut ppu, 43);
+        assert_eq!(ppu.mode(), Mode::HBlank);
+        tick_n(&mut ppu, LINE - 63);
+        assert_eq!(ppu.mode(), Mode::OamScan);
+        assert_eq!(ppu.readb(LY_ADDR), 1);
+    }
+
+    #[test]
+    pub fn test_vblank_and_frame_wrap() {
+        let mut ppu = Ppu::default();
+        ppu.writeb(LCDC_ADDR, 0x80);
+
+        tick_n(&mut ppu, LINE * 144);
+        assert_eq!(ppu.readb(LY_ADDR), 144);
+        assert_eq!(ppu.mode(), Mode::VBlank);
+
+        tick_n(&mut ppu, LINE * 10);
+        assert_eq!(ppu.readb(LY_ADDR), 0);
+        assert_eq!(ppu.mode(), Mode::OamScan);
+    }
+
+    #[test]
+    pub fn test_line_153_reads_0() {
+        let mut ppu = Ppu::default();
+        ppu.writeb(LCDC_ADDR, 0x80);
+
+        tick_n(&mut ppu, LINE * 153);
+        assert_eq!(ppu.readb(LY_ADDR), 153);
+        ppu.tick();
+        assert_eq!(ppu.readb(LY_ADDR), 0);
+        assert_eq!(ppu.mode(), Mode::VBlank);
+
+        tick_n(&mut ppu, LINE - 1);
+        assert_eq!(ppu.readb(LY_ADDR), 0);
+        assert_eq!(ppu.mode(), Mode::OamScan);
+        tick_n(&mut ppu, LINE);
+        assert_eq!(ppu.readb(LY_ADDR), 1);
+
+        // LYC=153 only matches for one M-cycle, LYC=0 matches from there to the end of line 0
+        assert_eq!(stat_per_frame(STAT_LYC_SOURCE, 153), 1);
+        assert_eq!(stat_per_frame(STAT_LYC_SOURCE, 0), 2);
+    }
+
+    #[test]
+    pub fn test_lcd_off() {
+        let mut ppu = Ppu::default();
+        ppu.writeb(LCDC_ADDR, 0x80);
+        tick_n(&mut ppu, LINE * 3 + 10);
+
+        ppu.writeb(LCDC_ADDR, 0x00);
+        assert_eq!(ppu.readb(LY_ADDR), 0);
+        tick_n(&mut ppu, LINE);
+        assert_eq!(ppu.readb(LY_ADDR), 0);
+        assert_eq!(ppu.readb(STAT_ADDR) & 0b11, 0);
+    }
+
+    #[test]
+    pub fn test_stat_register() {
+        let mut ppu = Ppu::default();
+        ppu.writeb(LCDC_ADDR, 0x80);
+        ppu.writeb(LYC_ADDR, 0);
+        ppu.writeb(STAT_ADDR, 0xFF);
+
+        // LYC=LY and the mode are read only
+        assert_eq!(ppu.readb(STAT_ADDR), 0xFE);
+        ppu.writeb(LY_ADDR, 0x42);
+        assert_eq!(ppu.readb(LY_ADDR), 0);
+    }
+
+    #[test]
+    pub fn test_vblank_interrupt() {
+        let mut ppu = Ppu::default();
+        ppu.writeb(LCDC_ADDR, 0x80);
+
+        assert_eq!(count_interrupts(&mut ppu, LINE * 144 - 1, Interrupt::VBlank), 0);
+        assert_ne!(ppu.tick() & Interrupt::VBlank.mask(), 0);
+        assert_eq!(ppu.readb(LY_ADDR), 144);
+        assert_eq!(count_interrupts(&mut ppu, LINE * 154, Interrupt::VBlank), 1);
+    }
+
+    #[test]
+    pub fn test_lyc_interrupt() {
+        let mut ppu = Ppu::default();
+        ppu.writeb(STAT_ADDR, STAT_LYC_SOURCE);
+        ppu.writeb(LYC_ADDR, 5);
+        ppu.writeb(LCDC_ADDR, 0x80);
+
+        assert_eq!(count_interrupts(&mut ppu, LINE * 5 - 1, Interrupt::Stat), 0);
+        assert_ne!(ppu.tick() & Interrupt::Stat.mask(), 0);
+        assert_eq!(ppu.readb(LY_ADDR), 5);
+        assert_eq!(count_interrupts(&mut ppu, LINE * 154 - 1, Interrupt::Stat), 0);
+    }
+
+    #[test]
+    pub fn test_stat_sources() {
+        assert_eq!(stat_per_frame(STAT_HBLANK_SOURCE, 0xFF), 144);
+        // plus the one when entering VBlank
+        assert_eq!(stat_per_frame(STAT_OAM_SOURCE, 0xFF), 145);
+        assert_eq!(stat_per_frame(STAT_VBLANK_SOURCE, 0xFF), 1);
+    }
+
+    #[test]
+    pub fn test_stat_blocking() {
+        // mode 2 follows mode 0 without the line going low
+        assert_eq!(stat_per_frame(STAT_HBLANK_SOURCE | STAT_OAM_SOURCE, 0xFF), 145);
+        // the LY=LYC match happens while the mode 1 source already holds the line
+        assert_eq!(stat_per_frame(STAT_VBLANK_SOURCE, 150), 1);
+        assert_eq!(stat_per_frame(STAT_VBLANK_SOURCE | STAT_LYC_SOURCE, 150), 1);
+        assert_eq!(stat_per_frame(STAT_LYC_SOURCE, 150), 1);
+        assert_eq!(stat_per_frame(STAT_HBLANK_SOURCE | STAT_LYC_SOURCE, 150), 145);
+    }
+}
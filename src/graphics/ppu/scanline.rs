@@ -0,0 +1,276 @@
+use crate::graphics::{
+    PIX_WHITE, SCREEN_WIDTH, shade,
+    ppu::{
+        LCDC_BG_ENABLE, LCDC_BG_MAP, LCDC_OBJ_ENABLE, LCDC_OBJ_SIZE, LCDC_TILE_DATA, LCDC_WINDOW_ENABLE,
+        LCDC_WINDOW_MAP, Ppu, Sprite,
+    },
+};
+
+const BG_MAP_LOW: usize = 0x1800;
+const BG_MAP_HIGH: usize = 0x1C00;
+
+impl Ppu {
+    ///Draw the current line into the framebuffer at once, from the registers as they are now
+    pub(super) fn render_line(&mut self) {
+        let ly = self.ly as usize;
+        let mut line = [PIX_WHITE; SCREEN_WIDTH];
+        // color indexes before the palette, sprites behind the background only show over color 0
+        let mut bg_colors = [0u8; SCREEN_WIDTH];
+
+        let window_visible =
+            self.lcdc & LCDC_WINDOW_ENABLE != 0 && self.window_triggered && self.wx <= 166;
+        let mut window_drawn = false;
+
+        if self.lcdc & LCDC_BG_ENABLE != 0 {
+            for x in 0..SCREEN_WIDTH {
+                let color = if window_visible && x + 7 >= self.wx as usize {
+                    window_drawn = true;
+                    let map = if self.lcdc & LCDC_WINDOW_MAP != 0 { BG_MAP_HIGH } else { BG_MAP_LOW };
+                    self.map_pixel(map, x + 7 - self.wx as usize, self.window_line as usize)
+                } else {
+                    let map = if self.lcdc & LCDC_BG_MAP != 0 { BG_MAP_HIGH } else { BG_MAP_LOW };
+                    let px = (x + self.scx as usize) & 0xFF;
+                    let py = (ly + self.scy as usize) & 0xFF;
+                    self.map_pixel(map, px, py)
+                };
+                bg_colors[x] = color;
+                line[x] = shade(self.bgp, color);
+            }
+        }
+
+        if window_drawn {
+            self.window_line += 1;
+        }
+
+        if self.lcdc & LCDC_OBJ_ENABLE != 0 {
+            self.render_sprites(&mut line, &bg_colors);
+        }
+
+        self.framebuffer[ly * SCREEN_WIDTH..(ly + 1) * SCREEN_WIDTH].copy_from_slice(&line);
+    }
+
+    fn render_sprites(&self, line: &mut [u8; SCREEN_WIDTH], bg_colors: &[u8; SCREEN_WIDTH]) {
+        // on DMG the sprite with the smallest x wins, then the first one in OAM
+        let mut sprites = self.line_sprites.clone();
+        sprites.sort_by_key(|sprite| sprite.x);
+
+        for (x, pixel) in line.iter_mut().enumerate() {
+            let Some((sprite, color)) = sprites
+                .iter()
+                .filter_map(|sprite| self.sprite_pixel(sprite, x).map(|color| (sprite, color)))
+                .find(|(_, color)| *color != 0)
+            else {
+                continue;
+            };
+
+            if sprite.attributes & Sprite::BG_PRIORITY != 0 && bg_colors[x] != 0 {
+                continue;
+            }
+            let palette = if sprite.attributes & Sprite::PALETTE != 0 { self.obp1 } else { self.obp0 };
+            *pixel = shade(palette, color);
+        }
+    }
+
+    ///Color index of a sprite at screen column x, if the sprite covers it
+    pub(super) fn sprite_pixel(&self, sprite: &Sprite, x: usize) -> Option<u8> {
+        let column = (x + 8).checked_sub(sprite.x as usize).filter(|col| *col < 8)?;
+        let height = if self.lcdc & LCDC_OBJ_SIZE != 0 { 16 } else { 8 };
+
+        // sprites are picked at OAM scan, OBJ size may have shrunk since
+        let mut row = (self.ly as usize + 16).checked_sub(sprite.y as usize).filter(|row| *row < height)?;
+        if sprite.attributes & Sprite::FLIP_Y != 0 {
+            row = height - 1 - row;
+        }
+        let column = if sprite.attributes & Sprite::FLIP_X != 0 { 7 - column } else { column };
+        let tile = if height == 16 { sprite.tile & 0xFE } else { sprite.tile };
+
+        // 8x16 sprites use two consecutive tiles, so the row can run into the second one
+        Some(self.tile_pixel(tile as usize * 16, column, row))
+    }
+
+    ///Color index of the pixel (x, y) of a 256x256 background map
+    fn map_pixel(&self, map: usize, x: usize, y: usize) -> u8 {
+        let tile = self.vram[map + (y / 8) * 32 + x / 8];
+        self.tile_pixel(self.bg_tile_addr(tile), x % 8, y % 8)
+    }
+
+    ///Offset in VRAM of a background or window tile
+    pub(super) fn bg_tile_addr(&self, tile: u8) -> usize {
+        if self.lcdc & LCDC_TILE_DATA != 0 {
+            tile as usize * 16
+        } else {
+            (0x1000 + tile as i8 as isize * 16) as usize
+        }
+    }
+
+    fn tile_pixel(&self, tile_addr: usize, x: usize, y: usize) -> u8 {
+        let low = self.vram[tile_addr + y * 2];
+        let high = self.vram[tile_addr + y * 2 + 1];
+        let bit = 7 - x;
+        (((high >> bit) & 1) << 1) | ((low >> bit) & 1)
+    }
+}
+
+//MARK: TEST
+
+#[cfg(test)]
+mod test {
+    use crate::graphics::{
+        PIX_BLACK, PIX_DARK_GRAY, PIX_LIGHT_GRAY, PIX_WHITE, SCREEN_WIDTH,
+        ppu::{BGP_ADDR, LCDC_ADDR, OBP0_ADDR, Ppu, SCX_ADDR, Sprite, WX_ADDR, WY_ADDR},
+    };
+
+    ///Frame with tile 1 full of color 3 and tile 2 full of color 1
+    fn ppu_with_tiles() -> Ppu {
+        let mut ppu = Ppu::default();
+        for row in 0..8 {
+            ppu.write_vram(0x8010 + row * 2, 0xFF);
+            ppu.write_vram(0x8011 + row * 2, 0xFF);
+            ppu.write_vram(0x8020 + row * 2, 0xFF);
+        }
+        ppu.writeb(BGP_ADDR, 0b11_10_01_00);
+        ppu.writeb(OBP0_ADDR, 0b11_10_01_00);
+        ppu
+    }
+
+    fn tick_n(ppu: &mut Ppu, n: usize) {
+        for _ in 0..n {
+            ppu.tick();
+        }
+    }
+
+    fn render_frame(ppu: &mut Ppu) {
+        tick_n(ppu, 114 * 154);
+    }
+
+    fn pixel(ppu: &Ppu, x: usize, y: usize) -> u8 {
+        ppu.framebuffer()[y * SCREEN_WIDTH + x]
+    }
+
+    #[test]
+    pub fn test_background_scroll() {
+        let mut ppu = ppu_with_tiles();
+        ppu.write_vram(0x9801, 1);
+        ppu.writeb(SCX_ADDR, 4);
+        ppu.writeb(LCDC_ADDR, 0x91);
+        render_frame(&mut ppu);
+
+        assert_eq!(pixel(&ppu, 3, 0), PIX_WHITE);
+        assert_eq!(pixel(&ppu, 4, 0), PIX_BLACK);
+        assert_eq!(pixel(&ppu, 11, 7), PIX_BLACK);
+        assert_eq!(pixel(&ppu, 12, 7), PIX_WHITE);
+        assert_eq!(pixel(&ppu, 4, 8), PIX_WHITE);
+    }
+
+    #[test]
+    pub fn test_signed_tile_data() {
+        let mut ppu = ppu_with_tiles();
+        // tile 0 at 0x9000 in the signed addressing mode
+        for row in 0..8 {
+            ppu.write_vram(0x9000 + row * 2, 0xFF);
+        }
+        ppu.writeb(LCDC_ADDR, 0x81);
+        render_frame(&mut ppu);
+
+        assert_eq!(pixel(&ppu, 0, 0), PIX_LIGHT_GRAY);
+    }
+
+    #[test]
+    pub fn test_window() {
+        let mut ppu = ppu_with_tiles();
+        ppu.write_vram(0x9C00, 1);
+        ppu.writeb(WY_ADDR, 10);
+        ppu.writeb(WX_ADDR, 7 + 20);
+        ppu.writeb(LCDC_ADDR, 0xF1);
+        render_frame(&mut ppu);
+
+        assert_eq!(pixel(&ppu, 20, 9), PIX_WHITE);
+        assert_eq!(pixel(&ppu, 19, 10), PIX_WHITE);
+        assert_eq!(pixel(&ppu, 20, 10), PIX_BLACK);
+        assert_eq!(pixel(&ppu, 27, 17), PIX_BLACK);
+        assert_eq!(pixel(&ppu, 20, 18), PIX_WHITE);
+    }
+
+    #[test]
+    pub fn test_window_trigger_latched() {
+        let mut ppu = ppu_with_tiles();
+        for i in 0..0x400 {
+            ppu.write_vram(0x9C00 + i, 1);
+        }
+        ppu.writeb(WY_ADDR, 200);
+        ppu.writeb(WX_ADDR, 7);
+        ppu.writeb(LCDC_ADDR, 0xF1);
+
+        // WY matches LY after the start of line 20, then moves away
+        tick_n(&mut ppu, 114 * 20 + 10);
+        ppu.writeb(WY_ADDR, 20);
+        tick_n(&mut ppu, 114 * 10);
+        ppu.writeb(WY_ADDR, 200);
+        tick_n(&mut ppu, 114 * 124 - 10);
+
+        assert_eq!(pixel(&ppu, 0, 19), PIX_WHITE);
+        assert_eq!(pixel(&ppu, 0, 20), PIX_BLACK);
+        assert_eq!(pixel(&ppu, 0, 143), PIX_BLACK);
+
+        // not carried over to the next frame
+        render_frame(&mut ppu);
+        assert_eq!(pixel(&ppu, 0, 20), PIX_WHITE);
+    }
+
+    #[test]
+    pub fn test_sprite_priority_and_flip() {
+        let mut ppu = ppu_with_tiles();
+        // half tile 3: only the left column is set
+        for row in 0..8 {
+            ppu.write_vram(0x8031 + row * 2, 0x80);
+        }
+        // sprite 0 at x = 10 with tile 2, sprite 1 at x = 8 with tile 1
+        for (i, byte) in [16, 18, 2, 0, 16, 16, 1, 0].into_iter().enumerate() {
+            ppu.write_oam(0xFE00 + i as u16, byte);
+        }
+        // flipped half tile at x = 40, behind the background
+        for (i, byte) in [16, 48, 3, 0x20].into_iter().enumerate() {
+            ppu.write_oam(0xFE08 + i as u16, byte);
+        }
+        ppu.writeb(LCDC_ADDR, 0x82);
+        render_frame(&mut ppu);
+
+        // the leftmost sprite wins where they overlap
+        assert_eq!(pixel(&ppu, 8, 0), PIX_BLACK);
+        assert_eq!(pixel(&ppu, 15, 0), PIX_BLACK);
+        assert_eq!(pixel(&ppu, 16, 0), PIX_LIGHT_GRAY);
+        assert_eq!(pixel(&ppu, 17, 0), PIX_LIGHT_GRAY);
+        assert_eq!(pixel(&ppu, 18, 0), PIX_WHITE);
+
+        assert_eq!(pixel(&ppu, 40, 0), PIX_WHITE);
+        assert_eq!(pixel(&ppu, 47, 0), PIX_DARK_GRAY);
+    }
+
+    #[test]
+    pub fn test_sprite_limit_per_line() {
+        let mut ppu = ppu_with_tiles();
+        for i in 0..11u16 {
+            for (j, byte) in [16, 8 + 10 * i as u8, 1, 0].into_iter().enumerate() {
+                ppu.write_oam(0xFE00 + i * 4 + j as u16, byte);
+            }
+        }
+        ppu.writeb(LCDC_ADDR, 0x82);
+        render_frame(&mut ppu);
+
+        assert_eq!(pixel(&ppu, 90, 0), PIX_BLACK);
+        assert_eq!(pixel(&ppu, 100, 0), PIX_WHITE);
+    }
+
+    #[test]
+    pub fn test_obj_size_shrunk_mid_line() {
+        let mut ppu = ppu_with_tiles();
+        // a flipped 8x16 sprite whose second half is on line 10
+        let sprite = Sprite { y: 16, x: 8, tile: 1, attributes: Sprite::FLIP_Y };
+        ppu.ly = 10;
+        ppu.writeb(LCDC_ADDR, 0x97);
+        assert_eq!(ppu.sprite_pixel(&sprite, 0), Some(0));
+
+        ppu.writeb(LCDC_ADDR, 0x93);
+        assert_eq!(ppu.sprite_pixel(&sprite, 0), None);
+    }
+}
@@ -1,7 +1,14 @@
-use std::error::Error;
+use std::{error::Error, path::PathBuf, rc::Rc, time::Duration};
+
+use crate::{
+    apps::screenshot::LinkPort,
+    graphics::{image::Palette, ppu::Renderer},
+    mem_bus::{cartridge::save::SaveConfig, mbc::rtc::SystemClock},
+};
 
 
 mod cpu;
+mod emulator;
 mod mem_bus;
 pub mod utils;
 mod apps;
@@ -11,7 +18,23 @@ pub mod graphics;
 const HELP_MSG :&str = "
 Usage :
 \tgb_emu dbg <rom_path> : launch a tiny debugger onto a rom
+\tgb_emu play <rom_path> : play a rom inside the terminal (needs 24 bits colors)
 \tgb_emu dasm <rom_path> : print the de-assemble rom 
+\tgb_emu info <rom_path> : print the cartridge header of a rom
+\tgb_emu test-blargg <dir> : run every blargg test rom of a directory and print the results
+\tgb_emu test-mooneye <dir> [--ppu fifo] : run every mooneye test rom of a directory and print the results
+\tgb_emu test-acid2 <dir> [--ppu fifo] : run dmg-acid2.gb from a directory and compare its frame with reference-dmg.png
+\tgb_emu screenshot <rom_path> --out <file.png|file.ppm> : run a rom without display and save a frame
+
+Options :
+\t--save-interval <secs> : flush the battery save every <secs> seconds, 0 to only save on exit (default 5)
+\t--force-save : use and overwrite a .sav file even if its size does not match the cartridge ram
+\t--frames <n> : frames to run before taking a screenshot (default 60)
+\t--out <path> : where to write the screenshot
+\t--palette <hex,hex,hex,hex> : screenshot and terminal colors from the lightest to the darkest shade
+\t--print-serial : print the bytes the rom sent over the link port after a screenshot
+\t--link <rom_path> : run a second rom plugged in the link port while taking a screenshot
+\t--ppu <scanline|fifo> : draw whole lines at once (default), or emulate the pixel FIFO for mid line effects
 ";
 
 fn main() -> Result<(),Box<dyn Error>> {
@@ -20,15 +43,55 @@ fn main() -> Result<(),Box<dyn Error>> {
     let _arg0 = args.next();
     let arg1 = args.next().map(|s|s.to_ascii_lowercase());
     let arg2 = args.next();
+    let options: Vec<String> = args.collect();
+    let clock = Rc::new(SystemClock);
 
     match (arg1.as_deref(),arg2.as_deref()) {
         (Some("help"),_) => println!("{HELP_MSG}"),
-        (Some("dbg"),Some(path)) => apps::debugger::debug(path)?,
+        (Some("dbg"),Some(path)) => {
+            let options = parse_options(&options)?;
+            apps::debugger::debug(path, options.save, options.renderer, clock)?
+        }
+        (Some("play"),Some(path)) => {
+            let options = parse_options(&options)?;
+            apps::terminal::play(path, options.save, &options.palette, options.renderer, clock)?
+        }
 
         (Some("deass"),Some(path)) |
         (Some("deassemble"),Some(path)) |
         (Some("deasm"),Some(path)) |
-        (Some("dasm"),Some(path))  => apps::deasm::desasm(path)?,
+        (Some("dasm"),Some(path))  => apps::deasm::desasm(path, clock)?,
+
+        (Some("info"),Some(path)) => apps::info::info(path)?,
+
+        (Some("test-blargg"),Some(dir)) => apps::harness::blargg::test_blargg(dir)?,
+        (Some("test-mooneye"),Some(dir)) => {
+            let options = parse_options(&options)?;
+            apps::harness::mooneye::test_mooneye(dir, options.renderer)?
+        }
+        (Some("test-acid2"),Some(dir)) => {
+            let options = parse_options(&options)?;
+            apps::harness::acid2::test_acid2(dir, options.renderer)?
+        }
+
+        (Some("screenshot"),Some(path)) => {
+            let options = parse_options(&options)?;
+            let out = options.out.ok_or_else(|| String::from("screenshot needs --out <path>"))?;
+            let link = match (options.link.as_deref(), options.print_serial) {
+                (Some(_), true) => Err(String::from("--print-serial and --link both need the link port"))?,
+                (Some(rom), false) => LinkPort::Rom(rom),
+                (None, print) => LinkPort::Capture { print },
+            };
+            apps::screenshot::screenshot(
+                path,
+                options.frames,
+                &out,
+                &options.palette,
+                options.renderer,
+                link,
+                clock,
+            )?
+        }
 
         (Some(x1),Some(x2)) => Err(format!("Unsuported args : {x1},{x2}"))?,
         (Some(x),None) => Err(format!("Unsuported args : {x}"))?,
@@ -36,4 +99,77 @@ fn main() -> Result<(),Box<dyn Error>> {
     }
     
     Ok(())
-}
\ No newline at end of file
+}
+
+#[derive(Debug)]
+struct Options {
+    save: SaveConfig,
+    renderer: Renderer,
+    frames: u32,
+    out: Option<PathBuf>,
+    palette: Palette,
+    print_serial: bool,
+    link: Option<PathBuf>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            save: SaveConfig::default(),
+            renderer: Renderer::default(),
+            frames: 60,
+            out: None,
+            palette: Palette::default(),
+            print_serial: false,
+            link: None,
+        }
+    }
+}
+
+fn parse_options(options: &[String]) -> Result<Options, String> {
+    let mut parsed = Options::default();
+    let config = &mut parsed.save;
+    let mut options = options.iter();
+
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "--force-save" => config.allow_size_mismatch = true,
+            "--print-serial" => parsed.print_serial = true,
+            "--save-interval" => {
+                let secs = options
+                    .next()
+                    .and_then(|s| s.parse::<u64>().ok())
+                    .ok_or_else(|| String::from("--save-interval expects a number of seconds"))?;
+                config.flush_interval = (secs != 0).then_some(Duration::from_secs(secs));
+            }
+            "--frames" => {
+                parsed.frames = options
+                    .next()
+                    .and_then(|s| s.parse::<u32>().ok())
+                    .ok_or_else(|| String::from("--frames expects a number of frames"))?;
+            }
+            "--out" => {
+                let out = options.next().ok_or_else(|| String::from("--out expects a path"))?;
+                parsed.out = Some(PathBuf::from(out));
+            }
+            "--link" => {
+                let link = options.next().ok_or_else(|| String::from("--link expects a rom path"))?;
+                parsed.link = Some(PathBuf::from(link));
+            }
+            "--palette" => {
+                let palette = options.next().ok_or_else(|| String::from("--palette expects 4 colors"))?;
+                parsed.palette = Palette::parse(palette).map_err(|err| err.to_string())?;
+            }
+            "--ppu" => {
+                parsed.renderer = match options.next().map(String::as_str) {
+                    Some("scanline") => Renderer::Scanline,
+                    Some("fifo") => Renderer::Fifo,
+                    _ => Err(String::from("--ppu expects scanline or fifo"))?,
+                }
+            }
+            x => Err(format!("Unsuported option : {x}"))?,
+        }
+    }
+
+    Ok(parsed)
+}
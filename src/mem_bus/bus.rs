@@ -0,0 +1,42 @@
+use crate::{
+    cpu::interrupts::Interrupt,
+    utils::{bytes_to_word, word_to_bytes},
+};
+
+const IF_ADDR: u16 = 0xFF0F;
+const IE_ADDR: u16 = 0xFFFF;
+
+///What the cpu sees of the rest of the machine
+///
+///The cpu is not M-cycle accurate: an instruction does all of its reads and writes first, then `tick` is called
+///once per M-cycle it takes. Peripherals see every access of an instruction as happening at its start, so timing
+///that depends on which cycle of an instruction touches the bus (mid-instruction register writes, OAM DMA
+///conflicts) is off by up to the length of the instruction.
+pub trait Bus {
+    fn readb(&self, addr: u16) -> u8;
+
+    fn writeb(&mut self, addr: u16, byte: u8);
+
+    ///Advance everything driven by the clock by one M-cycle, called after the accesses of the instruction
+    fn tick(&mut self);
+
+    fn readw(&self, addr: u16) -> u16 {
+        bytes_to_word(self.readb(addr), self.readb(addr.wrapping_add(1)))
+    }
+
+    fn writew(&mut self, addr: u16, word: u16) {
+        let (low, high) = word_to_bytes(word);
+        self.writeb(addr, low);
+        self.writeb(addr.wrapping_add(1), high);
+    }
+
+    ///Interrupts both requested and enabled
+    fn pending_interrupts(&self) -> u8 {
+        self.readb(IF_ADDR) & self.readb(IE_ADDR) & 0x1F
+    }
+
+    fn clear_interrupt(&mut self, interrupt: Interrupt) {
+        let if_flag = self.readb(IF_ADDR);
+        self.writeb(IF_ADDR, if_flag & !interrupt.mask());
+    }
+}
@@ -0,0 +1,699 @@
+use std::{error::Error, fmt::Display};
+
+const HEADER_END: usize = 0x014F;
+
+const LOGO_START: usize = 0x0104;
+const LOGO_END: usize = 0x0133;
+
+const TITLE_START: usize = 0x0134;
+const TITLE_END: usize = 0x0143;
+const MANUFACTURER_START: usize = 0x013F;
+const MANUFACTURER_END: usize = 0x0142;
+const CGB_FLAG_ADDR: usize = 0x0143;
+const NEW_LICENSEE_START: usize = 0x0144;
+const SGB_FLAG_ADDR: usize = 0x0146;
+const CARTRIDGE_TYPE_ADDR: usize = 0x0147;
+const ROM_SIZE_ADDR: usize = 0x0148;
+const RAM_SIZE_ADDR: usize = 0x0149;
+const DESTINATION_ADDR: usize = 0x014A;
+const OLD_LICENSEE_ADDR: usize = 0x014B;
+const VERSION_ADDR: usize = 0x014C;
+const HEADER_CHECKSUM_ADDR: usize = 0x014D;
+const GLOBAL_CHECKSUM_ADDR: usize = 0x014E;
+
+///Logo the boot rom compares against before starting the game
+pub const NINTENDO_LOGO: [u8; 48] = [
+    0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0C, 0x00, 0x0D,
+    0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E, 0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99,
+    0xBB, 0xBB, 0x67, 0x63, 0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E,
+];
+
+/// Old licensee value telling that the new licensee code must be used instead
+const USE_NEW_LICENSEE: u8 = 0x33;
+
+///Decoded cartridge header, located at 0x0100 -> 0x014F
+#[derive(Debug, Clone)]
+pub struct CartridgeHeader {
+    pub title: String,
+    pub manufacturer_code: Option<String>,
+    pub cgb_flag: CgbFlag,
+    pub sgb_flag: bool,
+    pub licensee: Licensee,
+    pub cartridge_type: CartridgeType,
+    pub rom_size_code: u8,
+    pub ram_size_code: u8,
+    pub destination: Destination,
+    pub version: u8,
+
+    pub logo_valid: bool,
+    pub header_checksum: u8,
+    pub computed_header_checksum: u8,
+    pub global_checksum: u16,
+    pub computed_global_checksum: u16,
+}
+
+impl CartridgeHeader {
+    pub fn parse(rom: &[u8]) -> Result<Self, HeaderError> {
+        if rom.len() <= HEADER_END {
+            return Err(HeaderError::TooShort(rom.len()));
+        }
+
+        let cgb_flag = CgbFlag::from(rom[CGB_FLAG_ADDR]);
+
+        // On CGB cartridges the end of the title area is reused
+        let (title_end, manufacturer_code) = if cgb_flag == CgbFlag::DmgOnly {
+            (TITLE_END, None)
+        } else {
+            let code = &rom[MANUFACTURER_START..=MANUFACTURER_END];
+            if code.iter().all(|b| b.is_ascii_uppercase() || b.is_ascii_digit()) {
+                (MANUFACTURER_START - 1, Some(ascii_to_string(code)))
+            } else {
+                (CGB_FLAG_ADDR - 1, None)
+            }
+        };
+        let title = ascii_to_string(&rom[TITLE_START..=title_end]);
+
+        let licensee = match rom[OLD_LICENSEE_ADDR] {
+            USE_NEW_LICENSEE => Licensee::New([rom[NEW_LICENSEE_START], rom[NEW_LICENSEE_START + 1]]),
+            code => Licensee::Old(code),
+        };
+
+        Ok(Self {
+            title,
+            manufacturer_code,
+            cgb_flag,
+            sgb_flag: rom[SGB_FLAG_ADDR] == 0x03,
+            licensee,
+            cartridge_type: CartridgeType(rom[CARTRIDGE_TYPE_ADDR]),
+            rom_size_code: rom[ROM_SIZE_ADDR],
+            ram_size_code: rom[RAM_SIZE_ADDR],
+            destination: Destination::from(rom[DESTINATION_ADDR]),
+            version: rom[VERSION_ADDR],
+
+            logo_valid: has_nintendo_logo(rom, 0),
+            header_checksum: rom[HEADER_CHECKSUM_ADDR],
+            computed_header_checksum: compute_header_checksum(rom),
+            global_checksum: u16::from_be_bytes([rom[GLOBAL_CHECKSUM_ADDR], rom[GLOBAL_CHECKSUM_ADDR + 1]]),
+            computed_global_checksum: compute_global_checksum(rom),
+        })
+    }
+
+    ///The boot rom refuses to start if this is false
+    pub fn is_header_checksum_valid(&self) -> bool {
+        self.header_checksum == self.computed_header_checksum
+    }
+
+    ///Not verified by the hardware, but a good hint of a bad dump
+    pub fn is_global_checksum_valid(&self) -> bool {
+        self.global_checksum == self.computed_global_checksum
+    }
+
+    ///Size of the rom in bytes, None if the size code is unknown
+    pub fn rom_size(&self) -> Option<usize> {
+        match self.rom_size_code {
+            0x00..=0x08 => Some(0x8000 << self.rom_size_code),
+            0x52 => Some(72 * 0x4000),
+            0x53 => Some(80 * 0x4000),
+            0x54 => Some(96 * 0x4000),
+            _ => None,
+        }
+    }
+
+    ///Number of 16 KiB rom banks
+    pub fn rom_banks(&self) -> Option<usize> {
+        self.rom_size().map(|size| size / 0x4000)
+    }
+
+    ///Size of the external ram in bytes, None if the size code is unknown
+    pub fn ram_size(&self) -> Option<usize> {
+        match self.ram_size_code {
+            0x00 | 0x01 => Some(0),
+            0x02 => Some(0x2000),
+            0x03 => Some(0x8000),
+            0x04 => Some(0x20000),
+            0x05 => Some(0x10000),
+            _ => None,
+        }
+    }
+}
+
+impl Display for CartridgeHeader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let checksum_status = |ok: bool| if ok { "ok" } else { "MISMATCH" };
+
+        writeln!(f, "Title            : {}", self.title)?;
+        if let Some(code) = &self.manufacturer_code {
+            writeln!(f, "Manufacturer     : {code}")?;
+        }
+        writeln!(f, "CGB flag         : {}", self.cgb_flag)?;
+        writeln!(f, "SGB support      : {}", self.sgb_flag)?;
+        writeln!(f, "Licensee         : {}", self.licensee)?;
+        writeln!(f, "Cartridge type   : 0x{:02X} ({})", self.cartridge_type.0, self.cartridge_type)?;
+        match self.rom_size() {
+            Some(size) => writeln!(f, "ROM size         : {} KiB ({} banks)", size / 1024, self.rom_banks().unwrap_or(0))?,
+            None => writeln!(f, "ROM size         : unknown (0x{:02X})", self.rom_size_code)?,
+        }
+        match self.ram_size() {
+            Some(size) => writeln!(f, "RAM size         : {} KiB", size / 1024)?,
+            None => writeln!(f, "RAM size         : unknown (0x{:02X})", self.ram_size_code)?,
+        }
+        writeln!(f, "Destination      : {}", self.destination)?;
+        writeln!(f, "Version          : {}", self.version)?;
+        writeln!(f, "Nintendo logo    : {}", checksum_status(self.logo_valid))?;
+        writeln!(
+            f,
+            "Header checksum  : 0x{:02X} (computed 0x{:02X}, {})",
+            self.header_checksum,
+            self.computed_header_checksum,
+            checksum_status(self.is_header_checksum_valid())
+        )?;
+        write!(
+            f,
+            "Global checksum  : 0x{:04X} (computed 0x{:04X}, {})",
+            self.global_checksum,
+            self.computed_global_checksum,
+            checksum_status(self.is_global_checksum_valid())
+        )
+    }
+}
+
+///True if the header of the game starting at `base` holds the Nintendo logo
+pub fn has_nintendo_logo(rom: &[u8], base: usize) -> bool {
+    rom.get(base + LOGO_START..=base + LOGO_END) == Some(&NINTENDO_LOGO[..])
+}
+
+fn ascii_to_string(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .take_while(|b| **b != 0)
+        .map(|b| if b.is_ascii_graphic() || *b == b' ' { *b as char } else { '?' })
+        .collect::<String>()
+        .trim_end()
+        .to_string()
+}
+
+fn compute_header_checksum(rom: &[u8]) -> u8 {
+    rom[TITLE_START..=VERSION_ADDR]
+        .iter()
+        .fold(0u8, |acc, byte| acc.wrapping_sub(*byte).wrapping_sub(1))
+}
+
+fn compute_global_checksum(rom: &[u8]) -> u16 {
+    rom.iter()
+        .enumerate()
+        .filter(|(i, _)| *i != GLOBAL_CHECKSUM_ADDR && *i != GLOBAL_CHECKSUM_ADDR + 1)
+        .fold(0u16, |acc, (_, byte)| acc.wrapping_add(*byte as u16))
+}
+
+//MARK: Header fields
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CgbFlag {
+    DmgOnly,
+    CgbEnhanced,
+    CgbOnly,
+}
+
+impl From<u8> for CgbFlag {
+    fn from(value: u8) -> Self {
+        // the hardware only looks at bit 7
+        match value {
+            0xC0 => CgbFlag::CgbOnly,
+            _ if value & 0x80 != 0 => CgbFlag::CgbEnhanced,
+            _ => CgbFlag::DmgOnly,
+        }
+    }
+}
+
+impl Display for CgbFlag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CgbFlag::DmgOnly => write!(f, "DMG only"),
+            CgbFlag::CgbEnhanced => write!(f, "CGB enhanced"),
+            CgbFlag::CgbOnly => write!(f, "CGB only"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Destination {
+    Japan,
+    Overseas,
+    Unknown(u8),
+}
+
+impl From<u8> for Destination {
+    fn from(value: u8) -> Self {
+        match value {
+            0x00 => Destination::Japan,
+            0x01 => Destination::Overseas,
+            code => Destination::Unknown(code),
+        }
+    }
+}
+
+impl Display for Destination {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Destination::Japan => write!(f, "Japan"),
+            Destination::Overseas => write!(f, "Overseas"),
+            Destination::Unknown(code) => write!(f, "unknown (0x{code:02X})"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Licensee {
+    Old(u8),
+    New([u8; 2]),
+}
+
+impl Licensee {
+    pub fn publisher(&self) -> Option<&'static str> {
+        match self {
+            Licensee::Old(code) => old_licensee_name(*code),
+            Licensee::New(code) => new_licensee_name(code),
+        }
+    }
+}
+
+impl Display for Licensee {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = self.publisher().unwrap_or("unknown");
+        match self {
+            Licensee::Old(code) => write!(f, "{name} (old code 0x{code:02X})"),
+            Licensee::New(code) => write!(f, "{name} (new code \"{}\")", ascii_to_string(code)),
+        }
+    }
+}
+
+const fn old_licensee_name(code: u8) -> Option<&'static str> {
+    Some(match code {
+        0x00 => "None",
+        0x01 => "Nintendo",
+        0x08 => "Capcom",
+        0x09 => "HOT-B",
+        0x0A => "Jaleco",
+        0x0B => "Coconuts Japan",
+        0x0C => "Elite Systems",
+        0x13 => "EA (Electronic Arts)",
+        0x18 => "Hudson Soft",
+        0x19 => "ITC Entertainment",
+        0x1A => "Yanoman",
+        0x1D => "Japan Clary",
+        0x1F => "Virgin Games Ltd.",
+        0x24 => "PCM Complete",
+        0x25 => "San-X",
+        0x28 => "Kemco",
+        0x29 => "SETA Corporation",
+        0x30 => "Infogrames",
+        0x31 => "Nintendo",
+        0x32 => "Bandai",
+        0x34 => "Konami",
+        0x35 => "HectorSoft",
+        0x38 => "Capcom",
+        0x39 => "Banpresto",
+        0x3C => "Entertainment Interactive",
+        0x3E => "Gremlin",
+        0x41 => "Ubi Soft",
+        0x42 => "Atlus",
+        0x44 => "Malibu Interactive",
+        0x46 => "Angel",
+        0x47 => "Spectrum HoloByte",
+        0x49 => "Irem",
+        0x4A => "Virgin Games Ltd.",
+        0x4D => "Malibu Interactive",
+        0x4F => "U.S. Gold",
+        0x50 => "Absolute",
+        0x51 => "Acclaim Entertainment",
+        0x52 => "Activision",
+        0x53 => "Sammy USA Corporation",
+        0x54 => "GameTek",
+        0x55 => "Park Place",
+        0x56 => "LJN",
+        0x57 => "Matchbox",
+        0x59 => "Milton Bradley Company",
+        0x5A => "Mindscape",
+        0x5B => "Romstar",
+        0x5C => "Naxat Soft",
+        0x5D => "Tradewest",
+        0x60 => "Titus Interactive",
+        0x61 => "Virgin Games Ltd.",
+        0x67 => "Ocean Software",
+        0x69 => "EA (Electronic Arts)",
+        0x6E => "Elite Systems",
+        0x6F => "Electro Brain",
+        0x70 => "Infogrames",
+        0x71 => "Interplay Entertainment",
+        0x72 => "Broderbund",
+        0x73 => "Sculptured Software",
+        0x75 => "The Sales Curve Limited",
+        0x78 => "THQ",
+        0x79 => "Accolade",
+        0x7A => "Triffix Entertainment",
+        0x7C => "MicroProse",
+        0x7F => "Kemco",
+        0x80 => "Misawa Entertainment",
+        0x83 => "LOZC G.",
+        0x86 => "Tokuma Shoten",
+        0x8B => "Bullet-Proof Software",
+        0x8C => "Vic Tokai Corp.",
+        0x8E => "Ape Inc.",
+        0x8F => "I'Max",
+        0x91 => "Chunsoft Co.",
+        0x92 => "Video System",
+        0x93 => "Tsubaraya Productions",
+        0x95 => "Varie",
+        0x96 => "Yonezawa/S'Pal",
+        0x97 => "Kemco",
+        0x99 => "Arc",
+        0x9A => "Nihon Bussan",
+        0x9B => "Tecmo",
+        0x9C => "Imagineer",
+        0x9D => "Banpresto",
+        0x9F => "Nova",
+        0xA1 => "Hori Electric",
+        0xA2 => "Bandai",
+        0xA4 => "Konami",
+        0xA6 => "Kawada",
+        0xA7 => "Takara",
+        0xA9 => "Technos Japan",
+        0xAA => "Broderbund",
+        0xAC => "Toei Animation",
+        0xAD => "Toho",
+        0xAF => "Namco",
+        0xB0 => "Acclaim Entertainment",
+        0xB1 => "ASCII Corporation or Nexsoft",
+        0xB2 => "Bandai",
+        0xB4 => "Square Enix",
+        0xB6 => "HAL Laboratory",
+        0xB7 => "SNK",
+        0xB9 => "Pony Canyon",
+        0xBA => "Culture Brain",
+        0xBB => "Sunsoft",
+        0xBD => "Sony Imagesoft",
+        0xBF => "Sammy Corporation",
+        0xC0 => "Taito",
+        0xC2 => "Kemco",
+        0xC3 => "Square",
+        0xC4 => "Tokuma Shoten",
+        0xC5 => "Data East",
+        0xC6 => "Tonkin House",
+        0xC8 => "Koei",
+        0xC9 => "UFL",
+        0xCA => "Ultra Games",
+        0xCB => "VAP, Inc.",
+        0xCC => "Use Corporation",
+        0xCD => "Meldac",
+        0xCE => "Pony Canyon",
+        0xCF => "Angel",
+        0xD0 => "Taito",
+        0xD1 => "SOFEL",
+        0xD2 => "Quest",
+        0xD3 => "Sigma Enterprises",
+        0xD4 => "ASK Kodansha Co.",
+        0xD6 => "Naxat Soft",
+        0xD7 => "Copya System",
+        0xD9 => "Banpresto",
+        0xDA => "Tomy",
+        0xDB => "LJN",
+        0xDD => "Nippon Computer Systems",
+        0xDE => "Human Ent.",
+        0xDF => "Altron",
+        0xE0 => "Jaleco",
+        0xE1 => "Towa Chiki",
+        0xE2 => "Yutaka",
+        0xE3 => "Varie",
+        0xE5 => "Epoch",
+        0xE7 => "Athena",
+        0xE8 => "Asmik Ace Entertainment",
+        0xE9 => "Natsume",
+        0xEA => "King Records",
+        0xEB => "Atlus",
+        0xEC => "Epic/Sony Records",
+        0xEE => "IGS",
+        0xF0 => "A Wave",
+        0xF3 => "Extreme Entertainment",
+        0xFF => "LJN",
+        _ => return None,
+    })
+}
+
+fn new_licensee_name(code: &[u8; 2]) -> Option<&'static str> {
+    Some(match code {
+        b"00" => "None",
+        b"01" => "Nintendo Research & Development 1",
+        b"08" => "Capcom",
+        b"13" => "EA (Electronic Arts)",
+        b"18" => "Hudson Soft",
+        b"19" => "B-AI",
+        b"20" => "KSS",
+        b"22" => "Planning Office WADA",
+        b"24" => "PCM Complete",
+        b"25" => "San-X",
+        b"28" => "Kemco",
+        b"29" => "SETA Corporation",
+        b"30" => "Viacom",
+        b"31" => "Nintendo",
+        b"32" => "Bandai",
+        b"33" => "Ocean Software/Acclaim Entertainment",
+        b"34" => "Konami",
+        b"35" => "HectorSoft",
+        b"37" => "Taito",
+        b"38" => "Hudson Soft",
+        b"39" => "Banpresto",
+        b"41" => "Ubi Soft",
+        b"42" => "Atlus",
+        b"44" => "Malibu Interactive",
+        b"46" => "Angel",
+        b"47" => "Bullet-Proof Software",
+        b"49" => "Irem",
+        b"50" => "Absolute",
+        b"51" => "Acclaim Entertainment",
+        b"52" => "Activision",
+        b"53" => "Sammy USA Corporation",
+        b"54" => "Konami",
+        b"55" => "Hi Tech Expressions",
+        b"56" => "LJN",
+        b"57" => "Matchbox",
+        b"58" => "Mattel",
+        b"59" => "Milton Bradley Company",
+        b"60" => "Titus Interactive",
+        b"61" => "Virgin Games Ltd.",
+        b"64" => "Lucasfilm Games",
+        b"67" => "Ocean Software",
+        b"69" => "EA (Electronic Arts)",
+        b"70" => "Infogrames",
+        b"71" => "Interplay Entertainment",
+        b"72" => "Broderbund",
+        b"73" => "Sculptured Software",
+        b"75" => "The Sales Curve Limited",
+        b"78" => "THQ",
+        b"79" => "Accolade",
+        b"80" => "Misawa Entertainment",
+        b"83" => "LOZC G.",
+        b"86" => "Tokuma Shoten",
+        b"87" => "Tsukuda Original",
+        b"91" => "Chunsoft Co.",
+        b"92" => "Video System",
+        b"93" => "Ocean Software/Acclaim Entertainment",
+        b"95" => "Varie",
+        b"96" => "Yonezawa/S'Pal",
+        b"97" => "Kaneko",
+        b"99" => "Pack-In-Video",
+        b"9H" => "Bottom Up",
+        b"A4" => "Konami (Yu-Gi-Oh!)",
+        b"BL" => "MTO",
+        b"DK" => "Kodansha",
+        _ => return None,
+    })
+}
+
+///Raw cartridge type byte, located at 0x0147
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CartridgeType(pub u8);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mapper {
+    RomOnly,
+    Mbc1,
+    Mbc2,
+    Mbc3,
+    Mbc5,
+    Mbc6,
+    Mbc7,
+    Mmm01,
+    PocketCamera,
+    BandaiTama5,
+    HuC3,
+    HuC1,
+    Unknown,
+}
+
+impl CartridgeType {
+    pub const fn mapper(&self) -> Mapper {
+        match self.0 {
+            0x00 | 0x08 | 0x09 => Mapper::RomOnly,
+            0x01..=0x03 => Mapper::Mbc1,
+            0x05 | 0x06 => Mapper::Mbc2,
+            0x0B..=0x0D => Mapper::Mmm01,
+            0x0F..=0x13 => Mapper::Mbc3,
+            0x19..=0x1E => Mapper::Mbc5,
+            0x20 => Mapper::Mbc6,
+            0x22 => Mapper::Mbc7,
+            0xFC => Mapper::PocketCamera,
+            0xFD => Mapper::BandaiTama5,
+            0xFE => Mapper::HuC3,
+            0xFF => Mapper::HuC1,
+            _ => Mapper::Unknown,
+        }
+    }
+
+    pub const fn has_ram(&self) -> bool {
+        matches!(
+            self.0,
+            0x02 | 0x03 | 0x08 | 0x09 | 0x0C | 0x0D | 0x10 | 0x12 | 0x13 | 0x1A | 0x1B | 0x1D | 0x1E | 0x22 | 0xFF
+        )
+    }
+
+    pub const fn has_battery(&self) -> bool {
+        matches!(
+            self.0,
+            0x03 | 0x06 | 0x09 | 0x0D | 0x0F | 0x10 | 0x13 | 0x1B | 0x1E | 0x22 | 0xFF
+        )
+    }
+
+    pub const fn has_timer(&self) -> bool {
+        matches!(self.0, 0x0F | 0x10)
+    }
+
+    pub const fn has_rumble(&self) -> bool {
+        matches!(self.0, 0x1C..=0x1E | 0x22)
+    }
+}
+
+impl Display for CartridgeType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.mapper() {
+            Mapper::RomOnly => write!(f, "ROM")?,
+            Mapper::Mbc1 => write!(f, "MBC1")?,
+            Mapper::Mbc2 => write!(f, "MBC2")?,
+            Mapper::Mbc3 => write!(f, "MBC3")?,
+            Mapper::Mbc5 => write!(f, "MBC5")?,
+            Mapper::Mbc6 => write!(f, "MBC6")?,
+            Mapper::Mbc7 => write!(f, "MBC7+SENSOR")?,
+            Mapper::Mmm01 => write!(f, "MMM01")?,
+            Mapper::PocketCamera => write!(f, "POCKET CAMERA")?,
+            Mapper::BandaiTama5 => write!(f, "BANDAI TAMA5")?,
+            Mapper::HuC3 => write!(f, "HuC3")?,
+            Mapper::HuC1 => write!(f, "HuC1")?,
+            Mapper::Unknown => return write!(f, "unknown"),
+        }
+        if self.mapper() == Mapper::RomOnly && !self.has_ram() {
+            write!(f, " ONLY")?;
+        }
+        if self.has_timer() {
+            write!(f, "+TIMER")?;
+        }
+        if self.has_rumble() {
+            write!(f, "+RUMBLE")?;
+        }
+        if self.has_ram() {
+            write!(f, "+RAM")?;
+        }
+        if self.has_battery() {
+            write!(f, "+BATTERY")?;
+        }
+        Ok(())
+    }
+}
+
+//MARK: Errors
+
+#[derive(Debug, Clone, Copy)]
+pub enum HeaderError {
+    TooShort(usize),
+}
+
+impl Display for HeaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HeaderError::TooShort(len) => write!(
+                f,
+                "rom is too short to contain a header ({len} bytes, at least {} needed)",
+                HEADER_END + 1
+            ),
+        }
+    }
+}
+
+impl Error for HeaderError {}
+
+//MARK: TEST
+
+#[cfg(test)]
+mod test {
+    use crate::mem_bus::cartridge::header::{CartridgeHeader, CgbFlag, Licensee, Mapper, NINTENDO_LOGO};
+
+    fn make_rom() -> Vec<u8> {
+        let mut rom = vec![0; 0x8000];
+        rom[0x0134..0x0134 + 8].copy_from_slice(b"TESTGAME");
+        rom[0x0147] = 0x13; // MBC3+RAM+BATTERY
+        rom[0x0148] = 0x01;
+        rom[0x0149] = 0x03;
+        rom[0x014A] = 0x01;
+        rom[0x014B] = 0x33;
+        rom[0x0144..=0x0145].copy_from_slice(b"01");
+
+        let checksum = rom[0x0134..=0x014C].iter().fold(0u8, |acc, b| acc.wrapping_sub(*b).wrapping_sub(1));
+        rom[0x014D] = checksum;
+        let global = rom.iter().fold(0u16, |acc, b| acc.wrapping_add(*b as u16));
+        rom[0x014E..=0x014F].copy_from_slice(&global.to_be_bytes());
+        rom
+    }
+
+    #[test]
+    pub fn test_parse_header() {
+        let header = CartridgeHeader::parse(&make_rom()).unwrap();
+
+        assert_eq!(header.title, "TESTGAME");
+        assert_eq!(header.cgb_flag, CgbFlag::DmgOnly);
+        assert_eq!(header.licensee, Licensee::New(*b"01"));
+        assert_eq!(header.cartridge_type.mapper(), Mapper::Mbc3);
+        assert!(header.cartridge_type.has_battery());
+        assert_eq!(header.rom_size(), Some(0x10000));
+        assert_eq!(header.rom_banks(), Some(4));
+        assert_eq!(header.ram_size(), Some(0x8000));
+        assert!(header.is_header_checksum_valid());
+        assert!(header.is_global_checksum_valid());
+        assert!(!header.logo_valid);
+
+        let mut rom = make_rom();
+        rom[0x0104..=0x0133].copy_from_slice(&NINTENDO_LOGO);
+        assert!(CartridgeHeader::parse(&rom).unwrap().logo_valid);
+    }
+
+    #[test]
+    pub fn test_bad_checksums() {
+        let mut rom = make_rom();
+        rom[0x0140] ^= 0xFF;
+        let header = CartridgeHeader::parse(&rom).unwrap();
+
+        assert!(!header.is_header_checksum_valid());
+        assert!(!header.is_global_checksum_valid());
+    }
+
+    #[test]
+    pub fn test_too_short() {
+        assert!(CartridgeHeader::parse(&[0; 0x100]).is_err());
+    }
+
+    #[test]
+    pub fn test_cgb_flag() {
+        assert_eq!(CgbFlag::from(0x00), CgbFlag::DmgOnly);
+        assert_eq!(CgbFlag::from(0x41), CgbFlag::DmgOnly);
+        assert_eq!(CgbFlag::from(0x80), CgbFlag::CgbEnhanced);
+        assert_eq!(CgbFlag::from(0x84), CgbFlag::CgbEnhanced);
+        assert_eq!(CgbFlag::from(0xC0), CgbFlag::CgbOnly);
+    }
+}
@@ -0,0 +1,122 @@
+use std::{error::Error, fmt::Display, io::Read, path::Path};
+
+use crate::mem_bus::cartridge::header::{CartridgeHeader, HeaderError};
+
+/// Biggest rom the cartridge header can describe, 512 banks of 16 KiB
+pub const MAX_ROM_SIZE: usize = 0x80_0000;
+
+///Read a whole rom file, checking its size against the header
+pub fn read_rom(path: &Path) -> Result<Vec<u8>, RomError> {
+    let file = std::fs::File::open(path)?;
+
+    // read one byte more than allowed to detect oversized files without loading them fully
+    let mut bytes = Vec::new();
+    file.take(MAX_ROM_SIZE as u64 + 1).read_to_end(&mut bytes)?;
+    if bytes.len() > MAX_ROM_SIZE {
+        return Err(RomError::TooLarge);
+    }
+
+    let header = CartridgeHeader::parse(&bytes)?;
+    match header.rom_size() {
+        Some(expected) if expected != bytes.len() => Err(RomError::SizeMismatch {
+            expected,
+            found: bytes.len(),
+        }),
+        Some(_) => Ok(bytes),
+        None => Err(RomError::UnknownRomSize(header.rom_size_code)),
+    }
+}
+
+//MARK: Errors
+
+#[derive(Debug)]
+pub enum RomError {
+    Io(std::io::Error),
+    TooLarge,
+    Header(HeaderError),
+    UnknownRomSize(u8),
+    SizeMismatch { expected: usize, found: usize },
+}
+
+impl Display for RomError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RomError::Io(err) => write!(f, "could not read the rom : {err}"),
+            RomError::TooLarge => write!(f, "rom is bigger than the {} MiB maximum", MAX_ROM_SIZE >> 20),
+            RomError::Header(err) => write!(f, "invalid rom header : {err}"),
+            RomError::UnknownRomSize(code) => write!(f, "unknown rom size code in header : 0x{code:02X}"),
+            RomError::SizeMismatch { expected, found } => write!(
+                f,
+                "rom size does not match its header : header says 0x{expected:X} bytes, file has 0x{found:X} bytes"
+            ),
+        }
+    }
+}
+
+impl Error for RomError {}
+
+impl From<std::io::Error> for RomError {
+    fn from(value: std::io::Error) -> Self {
+        RomError::Io(value)
+    }
+}
+
+impl From<HeaderError> for RomError {
+    fn from(value: HeaderError) -> Self {
+        RomError::Header(value)
+    }
+}
+
+//MARK: TEST
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use crate::mem_bus::cartridge::loader::{RomError, read_rom};
+
+    fn write_temp_rom(name: &str, bytes: &[u8]) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("gb_emu_loader_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    #[test]
+    pub fn test_read_whole_rom() {
+        let mut rom = vec![0; 0x40000];
+        rom[0x0148] = 0x03; // 256 KiB
+        rom[0x3FFFF] = 0x42;
+        let path = write_temp_rom("whole.gb", &rom);
+
+        let bytes = read_rom(&path).unwrap();
+        assert_eq!(bytes.len(), 0x40000);
+        assert_eq!(bytes[0x3FFFF], 0x42);
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    pub fn test_size_mismatch() {
+        let mut rom = vec![0; 0x8000];
+        rom[0x0148] = 0x01; // 64 KiB
+        let path = write_temp_rom("mismatch.gb", &rom);
+
+        assert!(matches!(
+            read_rom(&path),
+            Err(RomError::SizeMismatch { expected: 0x10000, found: 0x8000 })
+        ));
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    pub fn test_missing_header() {
+        let path = write_temp_rom("tiny.gb", &[0; 0x20]);
+
+        assert!(matches!(read_rom(&path), Err(RomError::Header(_))));
+
+        fs::remove_file(path).unwrap();
+    }
+}
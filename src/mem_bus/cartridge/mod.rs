@@ -0,0 +1,128 @@
+use std::rc::Rc;
+
+use crate::mem_bus::{
+    cartridge::header::Mapper,
+    mbc::{
+        Mbc,
+        mbc2::MBC2_RAM_SIZE,
+        rtc::{Clock, RTC_TRAILER_SIZE},
+    },
+};
+
+pub mod header;
+pub mod loader;
+pub mod save;
+
+pub use header::CartridgeHeader;
+
+pub const ROM_BANK_SIZE: usize = 0x4000;
+pub const RAM_BANK_SIZE: usize = 0x2000;
+
+///Rom, external ram and memory bank controller of a game pak
+#[derive(Debug)]
+pub struct Cartridge {
+    pub header: Option<CartridgeHeader>,
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    mbc: Mbc,
+    /// Set when the battery backed data changed since the last save
+    dirty: bool,
+}
+
+impl Cartridge {
+    ///The clock drives the real time clock of MBC3 cartridges
+    pub fn from_bytes(bytes: &[u8], clock: Rc<dyn Clock>) -> Self {
+        let header = CartridgeHeader::parse(bytes).ok();
+
+        // pad the rom to a power of two number of banks, so bank numbers can be masked
+        let banks = bytes.len().div_ceil(ROM_BANK_SIZE).next_power_of_two().max(2);
+        let mut rom = bytes.to_vec();
+        rom.resize(banks * ROM_BANK_SIZE, 0xFF);
+
+        let ram_size = match &header {
+            Some(h) if h.cartridge_type.mapper() == Mapper::Mbc2 => MBC2_RAM_SIZE,
+            Some(h) => h.ram_size().unwrap_or(0),
+            None => 0,
+        };
+        let ram = vec![0; ram_size];
+
+        let mbc = Mbc::new(header.as_ref(), &rom, ram.len(), clock);
+
+        Self { header, rom, ram, mbc, dirty: false }
+    }
+
+    pub fn rom_banks(&self) -> usize {
+        self.rom.len() / ROM_BANK_SIZE
+    }
+
+    ///Bank currently mapped at 0x4000 -> 0x7FFF
+    pub fn rom_bank(&self) -> usize {
+        self.mbc.rom_bank()
+    }
+
+    pub fn mbc(&self) -> &Mbc {
+        &self.mbc
+    }
+
+    pub fn has_battery(&self) -> bool {
+        self.header.as_ref().is_some_and(|h| h.cartridge_type.has_battery())
+    }
+
+    pub fn has_rtc(&self) -> bool {
+        self.header.as_ref().is_some_and(|h| h.cartridge_type.has_timer())
+    }
+
+    pub fn ram_size(&self) -> usize {
+        self.ram.len()
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    ///Battery backed data: the external ram, followed by the rtc trailer if any
+    pub fn save_data(&mut self) -> Vec<u8> {
+        self.dirty = false;
+        let mut data = self.ram.clone();
+        if let Some(rtc) = self.mbc.rtc_mut() {
+            data.extend_from_slice(&rtc.save_trailer());
+        }
+        data
+    }
+
+    ///Restore data produced by `save_data`, a missing rtc trailer leaves the clock untouched
+    pub fn load_save_data(&mut self, data: &[u8]) {
+        let ram_len = self.ram.len().min(data.len());
+        self.ram[..ram_len].copy_from_slice(&data[..ram_len]);
+
+        if let Some(rtc) = self.mbc.rtc_mut()
+            && let Some(trailer) = data.get(self.ram.len()..self.ram.len() + RTC_TRAILER_SIZE)
+        {
+            rtc.load_trailer(trailer.try_into().expect("trailer has the right size"));
+        }
+    }
+}
+
+impl Cartridge {
+    ///0x0000 -> 0x7FFF
+    pub fn read_rom(&self, addr: u16) -> u8 {
+        self.mbc.read_rom(&self.rom, addr)
+    }
+
+    ///0x0000 -> 0x7FFF, writes go to the mbc registers
+    pub fn write_rom(&mut self, addr: u16, byte: u8) {
+        self.mbc.write_rom(addr, byte);
+    }
+
+    ///0xA000 -> 0xBFFF
+    pub fn read_ram(&self, addr: u16) -> u8 {
+        self.mbc.read_ram(&self.ram, addr)
+    }
+
+    ///0xA000 -> 0xBFFF, only a change of the battery backed data makes the cartridge dirty
+    pub fn write_ram(&mut self, addr: u16, byte: u8) {
+        if self.mbc.write_ram(&mut self.ram, addr, byte) {
+            self.dirty = true;
+        }
+    }
+}
@@ -0,0 +1,252 @@
+use std::{
+    error::Error,
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use crate::mem_bus::{cartridge::Cartridge, mbc::rtc::RTC_TRAILER_SIZE};
+
+pub const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy)]
+pub struct SaveConfig {
+    /// None disables the periodic flush, the save is then only written on exit
+    pub flush_interval: Option<Duration>,
+    /// Load and overwrite a save whose size does not match the cartridge
+    pub allow_size_mismatch: bool,
+}
+
+impl Default for SaveConfig {
+    fn default() -> Self {
+        Self {
+            flush_interval: Some(DEFAULT_FLUSH_INTERVAL),
+            allow_size_mismatch: false,
+        }
+    }
+}
+
+///`.sav` file of a battery backed cartridge
+#[derive(Debug)]
+pub struct SaveFile {
+    path: PathBuf,
+    config: SaveConfig,
+    last_flush: Instant,
+}
+
+impl SaveFile {
+    ///`path/to/game.gb` is saved in `path/to/game.sav`
+    pub fn path_for_rom(rom_path: &Path) -> PathBuf {
+        rom_path.with_extension("sav")
+    }
+
+    ///Load the save of the rom into the cartridge, if any.
+    ///Returns None when the cartridge has no battery, and so nothing to persist
+    pub fn open(rom_path: &Path, cartridge: &mut Cartridge, config: SaveConfig) -> Result<Option<Self>, SaveError> {
+        if !cartridge.has_battery() {
+            return Ok(None);
+        }
+
+        let path = Self::path_for_rom(rom_path);
+        if path.exists() {
+            let data = fs::read(&path)?;
+            if !config.allow_size_mismatch && !is_valid_save_size(cartridge, data.len()) {
+                return Err(SaveError::SizeMismatch {
+                    path,
+                    expected: cartridge.ram_size(),
+                    found: data.len(),
+                });
+            }
+            cartridge.load_save_data(&data);
+        }
+
+        Ok(Some(Self {
+            path,
+            config,
+            last_flush: Instant::now(),
+        }))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    ///Write the save if the cartridge data changed since the last flush
+    pub fn flush(&mut self, cartridge: &mut Cartridge) -> Result<(), SaveError> {
+        self.last_flush = Instant::now();
+        if !cartridge.is_dirty() {
+            return Ok(());
+        }
+
+        // write then rename, so a crash never leaves a truncated save behind
+        let tmp_path = self.path.with_extension("sav.tmp");
+        fs::write(&tmp_path, cartridge.save_data())?;
+        fs::rename(&tmp_path, &self.path)?;
+
+        Ok(())
+    }
+
+    ///Flush if the configured interval elapsed, meant to be called from the emulation loop
+    pub fn tick(&mut self, cartridge: &mut Cartridge) -> Result<(), SaveError> {
+        match self.config.flush_interval {
+            Some(interval) if self.last_flush.elapsed() >= interval => self.flush(cartridge),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn is_valid_save_size(cartridge: &Cartridge, size: usize) -> bool {
+    let ram_size = cartridge.ram_size();
+    size == ram_size || (cartridge.has_rtc() && size == ram_size + RTC_TRAILER_SIZE)
+}
+
+//MARK: Errors
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(std::io::Error),
+    SizeMismatch {
+        path: PathBuf,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl Display for SaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaveError::Io(err) => write!(f, "could not access the save file : {err}"),
+            SaveError::SizeMismatch { path, expected, found } => write!(
+                f,
+                "save file {} is {found} bytes but the cartridge has {expected} bytes of ram, refusing to use it",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl Error for SaveError {}
+
+impl From<std::io::Error> for SaveError {
+    fn from(value: std::io::Error) -> Self {
+        SaveError::Io(value)
+    }
+}
+
+//MARK: TEST
+
+#[cfg(test)]
+mod test {
+    use std::{fs, path::PathBuf, rc::Rc};
+
+    use crate::mem_bus::{
+        cartridge::{
+            Cartridge,
+            save::{SaveConfig, SaveError, SaveFile},
+        },
+        mbc::rtc::{SystemClock, test::FakeClock},
+    };
+
+    fn battery_cartridge() -> Cartridge {
+        let mut rom = vec![0; 0x8000];
+        rom[0x0147] = 0x03; // MBC1+RAM+BATTERY
+        rom[0x0149] = 0x02; // 8 KiB
+        Cartridge::from_bytes(&rom, Rc::new(SystemClock))
+    }
+
+    fn rtc_cartridge(clock: &FakeClock) -> Cartridge {
+        let mut rom = vec![0; 0x8000];
+        rom[0x0147] = 0x10; // MBC3+TIMER+RAM+BATTERY
+        rom[0x0149] = 0x02; // 8 KiB
+        Cartridge::from_bytes(&rom, Rc::new(clock.clone()))
+    }
+
+    fn temp_rom_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("gb_emu_save_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir.join(name)
+    }
+
+    #[test]
+    pub fn test_save_round_trip() {
+        let rom_path = temp_rom_path("round_trip.gb");
+        let mut cartridge = battery_cartridge();
+        let mut save = SaveFile::open(&rom_path, &mut cartridge, SaveConfig::default()).unwrap().unwrap();
+
+        // nothing written, nothing flushed
+        save.flush(&mut cartridge).unwrap();
+        assert!(!save.path().exists());
+
+        cartridge.write_rom(0x0000, 0x0A);
+        cartridge.write_ram(0xA010, 0x42);
+        save.flush(&mut cartridge).unwrap();
+        assert!(!cartridge.is_dirty());
+
+        let mut reloaded = battery_cartridge();
+        SaveFile::open(&rom_path, &mut reloaded, SaveConfig::default()).unwrap();
+        reloaded.write_rom(0x0000, 0x0A);
+        assert_eq!(reloaded.read_ram(0xA010), 0x42);
+
+        fs::remove_file(save.path()).unwrap();
+    }
+
+    #[test]
+    pub fn test_rtc_save_round_trip() {
+        let rom_path = temp_rom_path("rtc_round_trip.gb");
+        let clock = FakeClock::default();
+        clock.advance(1_000_000);
+
+        let mut cartridge = rtc_cartridge(&clock);
+        let mut save = SaveFile::open(&rom_path, &mut cartridge, SaveConfig::default()).unwrap().unwrap();
+        cartridge.write_rom(0x0000, 0x0A);
+        cartridge.write_rom(0x4000, 0x09);
+        cartridge.write_ram(0xA000, 30);
+        save.flush(&mut cartridge).unwrap();
+
+        clock.advance(3600);
+        let mut reloaded = rtc_cartridge(&clock);
+        SaveFile::open(&rom_path, &mut reloaded, SaveConfig::default()).unwrap();
+        reloaded.write_rom(0x0000, 0x0A);
+        reloaded.write_rom(0x6000, 0x00);
+        reloaded.write_rom(0x6000, 0x01);
+        reloaded.write_rom(0x4000, 0x09);
+        assert_eq!(reloaded.read_ram(0xA000), 30);
+        reloaded.write_rom(0x4000, 0x0A);
+        assert_eq!(reloaded.read_ram(0xA000), 1);
+
+        fs::remove_file(save.path()).unwrap();
+    }
+
+    #[test]
+    pub fn test_dirty_on_change_only() {
+        let mut cartridge = battery_cartridge();
+
+        // ram disabled
+        cartridge.write_ram(0xA000, 0x42);
+        assert!(!cartridge.is_dirty());
+
+        cartridge.write_rom(0x0000, 0x0A);
+        cartridge.write_ram(0xA000, 0x00);
+        assert!(!cartridge.is_dirty());
+        cartridge.write_ram(0xA000, 0x42);
+        assert!(cartridge.is_dirty());
+    }
+
+    #[test]
+    pub fn test_refuse_size_mismatch() {
+        let rom_path = temp_rom_path("mismatch.gb");
+        fs::write(SaveFile::path_for_rom(&rom_path), [0x42; 0x100]).unwrap();
+
+        let mut cartridge = battery_cartridge();
+        let res = SaveFile::open(&rom_path, &mut cartridge, SaveConfig::default());
+        assert!(matches!(res, Err(SaveError::SizeMismatch { expected: 0x2000, found: 0x100, .. })));
+
+        let config = SaveConfig { allow_size_mismatch: true, ..Default::default() };
+        assert!(SaveFile::open(&rom_path, &mut cartridge, config).unwrap().is_some());
+        cartridge.write_rom(0x0000, 0x0A);
+        assert_eq!(cartridge.read_ram(0xA0FF), 0x42);
+
+        fs::remove_file(SaveFile::path_for_rom(&rom_path)).unwrap();
+    }
+}
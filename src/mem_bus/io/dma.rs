@@ -0,0 +1,78 @@
+const OAM_DMA_LENGTH: u8 = 0xA0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Idle,
+    /// Number of bytes already copied
+    Transferring(u8),
+}
+
+///OAM DMA, copies 0xXX00 -> 0xXX9F into OAM, one byte per M-cycle
+#[derive(Debug)]
+pub struct Dma {
+    register: u8,
+    /// High byte of the transfer running, latched from the register when it starts
+    source: u8,
+    state: State,
+    /// FF46 was written, the copy begins on the next M-cycle
+    starting: bool,
+    /// Last byte put on the bus by the transfer, what the CPU reads outside HRAM meanwhile
+    bus_value: u8,
+}
+
+impl Default for Dma {
+    fn default() -> Self {
+        Self { register: 0xFF, source: 0xFF, state: State::Idle, starting: false, bus_value: 0xFF }
+    }
+}
+
+impl Dma {
+    pub fn is_active(&self) -> bool {
+        matches!(self.state, State::Transferring(_))
+    }
+
+    pub fn bus_value(&self) -> u8 {
+        self.bus_value
+    }
+
+    pub fn read(&self) -> u8 {
+        self.register
+    }
+
+    ///Writing 0xFF46 (re)starts a transfer from `byte`00,
+    ///a transfer already running goes on during the start up cycle of the new one
+    pub fn write(&mut self, byte: u8) {
+        self.register = byte;
+        self.starting = true;
+    }
+
+    ///Advance by one M-cycle, returns the (source, OAM offset) of the byte to copy now
+    pub fn tick(&mut self) -> Option<(u16, u16)> {
+        let copy = match self.state {
+            State::Idle => None,
+            State::Transferring(index) => {
+                self.state = if index + 1 == OAM_DMA_LENGTH { State::Idle } else { State::Transferring(index + 1) };
+                Some((self.source_base() + index as u16, index as u16))
+            }
+        };
+
+        if self.starting {
+            self.starting = false;
+            self.source = self.register;
+            self.state = State::Transferring(0);
+        }
+        copy
+    }
+
+    ///Sources above 0xDFFF read the echo ram
+    fn source_base(&self) -> u16 {
+        match self.source {
+            0xE0..=0xFF => (self.source as u16 - 0x20) << 8,
+            high => (high as u16) << 8,
+        }
+    }
+
+    pub fn set_bus_value(&mut self, byte: u8) {
+        self.bus_value = byte;
+    }
+}
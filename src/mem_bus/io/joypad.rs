@@ -0,0 +1,195 @@
+use crate::mem_bus::io::JOYPAD_ADDR;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Button {
+    Right,
+    Left,
+    Up,
+    Down,
+    A,
+    B,
+    Select,
+    Start,
+}
+
+impl Button {
+    pub const ALL: [Button; 8] = [
+        Button::Right,
+        Button::Left,
+        Button::Up,
+        Button::Down,
+        Button::A,
+        Button::B,
+        Button::Select,
+        Button::Start,
+    ];
+
+    /// Names accepted by `from_name`, in the order of `ALL`
+    const NAMES: [&str; 8] = ["right", "left", "up", "down", "a", "b", "select", "start"];
+
+    pub fn from_name(name: &str) -> Option<Button> {
+        let index = Self::NAMES.iter().position(|n| n.eq_ignore_ascii_case(name))?;
+        Some(Self::ALL[index])
+    }
+
+    ///Bit of the button in its group, the bit is cleared while the button is pressed
+    const fn mask(self) -> u8 {
+        match self {
+            Button::Right | Button::A => 0b0001,
+            Button::Left | Button::B => 0b0010,
+            Button::Up | Button::Select => 0b0100,
+            Button::Down | Button::Start => 0b1000,
+        }
+    }
+
+    const fn is_dpad(self) -> bool {
+        matches!(self, Button::Right | Button::Left | Button::Up | Button::Down)
+    }
+}
+
+const SELECT_DPAD: u8 = 0b0001_0000;
+const SELECT_BUTTONS: u8 = 0b0010_0000;
+
+///P1 register, a line reads 0 while its button is pressed and its group selected
+#[derive(Debug)]
+pub struct Joypad {
+    /// Bits 4 and 5 as last written, a group is selected when its bit is 0
+    select: u8,
+
+    select_buttons: u8, //Start Select B and A buttons
+    dpad: u8,
+
+    /// P10 -> P13 as last seen, to detect high to low transitions
+    lines: u8,
+    interrupt: bool,
+}
+
+impl Default for Joypad {
+    fn default() -> Self {
+        Joypad {
+            select: SELECT_DPAD | SELECT_BUTTONS,
+
+            select_buttons: 0x0F,
+            dpad: 0x0F,
+
+            lines: 0x0F,
+            interrupt: false,
+        }
+    }
+}
+
+impl Joypad {
+    pub fn press(&mut self, button: Button) {
+        if button.is_dpad() {
+            self.dpad &= !button.mask();
+        } else {
+            self.select_buttons &= !button.mask();
+        }
+        self.update_lines();
+    }
+
+    pub fn release(&mut self, button: Button) {
+        if button.is_dpad() {
+            self.dpad |= button.mask();
+        } else {
+            self.select_buttons |= button.mask();
+        }
+        self.update_lines();
+    }
+
+    ///True once after a line went from high to low
+    pub fn take_interrupt(&mut self) -> bool {
+        std::mem::take(&mut self.interrupt)
+    }
+
+    ///With both groups selected a line is low if either of its buttons is pressed
+    fn read_lines(&self) -> u8 {
+        let mut lines = 0x0F;
+        if self.select & SELECT_DPAD == 0 {
+            lines &= self.dpad;
+        }
+        if self.select & SELECT_BUTTONS == 0 {
+            lines &= self.select_buttons;
+        }
+        lines
+    }
+
+    fn update_lines(&mut self) {
+        let lines = self.read_lines();
+        if self.lines & !lines != 0 {
+            self.interrupt = true;
+        }
+        self.lines = lines;
+    }
+
+    pub fn readb(&self, addr: u16) -> u8 {
+        debug_assert_eq!(addr, JOYPAD_ADDR);
+        0xC0 | self.select | self.read_lines()
+    }
+
+    pub fn writeb(&mut self, addr: u16, byte: u8) {
+        debug_assert_eq!(addr, JOYPAD_ADDR);
+        self.select = byte & (SELECT_DPAD | SELECT_BUTTONS);
+        self.update_lines();
+    }
+}
+
+//MARK: TEST
+
+#[cfg(test)]
+mod test {
+    use crate::mem_bus::io::{
+        JOYPAD_ADDR,
+        joypad::{Button, Joypad},
+    };
+
+    #[test]
+    pub fn test_groups() {
+        let mut joypad = Joypad::default();
+        joypad.press(Button::A);
+        joypad.press(Button::Left);
+        assert_eq!(joypad.readb(JOYPAD_ADDR), 0xFF);
+
+        joypad.writeb(JOYPAD_ADDR, 0x10);
+        assert_eq!(joypad.readb(JOYPAD_ADDR), 0xDE);
+        joypad.writeb(JOYPAD_ADDR, 0x20);
+        assert_eq!(joypad.readb(JOYPAD_ADDR), 0xED);
+        // both groups selected: the lines are ANDed
+        joypad.writeb(JOYPAD_ADDR, 0x00);
+        assert_eq!(joypad.readb(JOYPAD_ADDR), 0xCC);
+    }
+
+    #[test]
+    pub fn test_interrupt_on_press() {
+        let mut joypad = Joypad::default();
+        joypad.writeb(JOYPAD_ADDR, 0x20);
+
+        // buttons group not selected
+        joypad.press(Button::Start);
+        assert!(!joypad.take_interrupt());
+
+        joypad.press(Button::Down);
+        assert!(joypad.take_interrupt());
+        assert!(!joypad.take_interrupt());
+
+        joypad.release(Button::Down);
+        assert!(!joypad.take_interrupt());
+    }
+
+    #[test]
+    pub fn test_button_names() {
+        assert_eq!(Button::from_name("start"), Some(Button::Start));
+        assert_eq!(Button::from_name("A"), Some(Button::A));
+        assert_eq!(Button::from_name("turbo"), None);
+    }
+
+    #[test]
+    pub fn test_interrupt_on_select() {
+        let mut joypad = Joypad::default();
+        joypad.press(Button::B);
+        assert!(!joypad.take_interrupt());
+
+        joypad.writeb(JOYPAD_ADDR, 0x10);
+        assert!(joypad.take_interrupt());
+    }
+}
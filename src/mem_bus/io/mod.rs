@@ -0,0 +1,15 @@
+pub mod dma;
+pub mod joypad;
+pub mod serial;
+pub mod timer;
+
+pub const JOYPAD_ADDR: u16 = 0xFF00;
+
+pub const SB_ADDR: u16 = 0xFF01;
+pub const SC_ADDR: u16 = 0xFF02;
+
+pub const DIV_ADDR: u16 = 0xFF04;
+pub const TIMA_ADDR: u16 = 0xFF05;
+pub const TMA_ADDR: u16 = 0xFF06;
+pub const TAC_ADDR: u16 = 0xFF07;
+pub const DMA_ADDR: u16 = 0xFF46;
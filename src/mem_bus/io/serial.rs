@@ -0,0 +1,277 @@
+use std::{cell::RefCell, fmt::Debug, rc::Rc};
+
+use crate::mem_bus::io::{SB_ADDR, SC_ADDR};
+
+/// M-cycles to shift a whole byte with the internal clock, 8 bits at 8192 Hz
+const TRANSFER_CYCLES: u16 = 8 * 128;
+
+const SC_TRANSFER: u8 = 0x80;
+const SC_INTERNAL_CLOCK: u8 = 0x01;
+
+///What is plugged in the link port
+pub trait SerialLink: Debug {
+    ///Our clock shifted `byte` out, returns the byte shifted in from the other side
+    fn exchange(&mut self, byte: u8) -> u8;
+
+    ///While waiting on the external clock with `byte` in SB, the byte clocked in by the other side if any
+    fn poll_external(&mut self, byte: u8) -> Option<u8>;
+
+    ///We stopped waiting on the external clock, forget what was offered or received meanwhile
+    fn stop_listening(&mut self);
+}
+
+///Nothing plugged in: the line stays high and no external clock ever comes
+#[derive(Debug, Default)]
+pub struct Disconnected;
+
+impl SerialLink for Disconnected {
+    fn exchange(&mut self, _byte: u8) -> u8 {
+        0xFF
+    }
+
+    fn poll_external(&mut self, _byte: u8) -> Option<u8> {
+        None
+    }
+
+    fn stop_listening(&mut self) {}
+}
+
+///Records every byte sent, as a disconnected cable would otherwise lose them
+#[derive(Debug, Default)]
+pub struct Capture {
+    output: Rc<RefCell<Vec<u8>>>,
+}
+
+impl Capture {
+    ///Handle on the bytes sent so far, still readable once the link is owned by the bus
+    pub fn output(&self) -> Rc<RefCell<Vec<u8>>> {
+        self.output.clone()
+    }
+}
+
+impl SerialLink for Capture {
+    fn exchange(&mut self, byte: u8) -> u8 {
+        self.output.borrow_mut().push(byte);
+        0xFF
+    }
+
+    fn poll_external(&mut self, _byte: u8) -> Option<u8> {
+        None
+    }
+
+    fn stop_listening(&mut self) {}
+}
+
+#[derive(Debug, Default)]
+struct Wire {
+    /// SB of each side while it waits for the other side clock
+    waiting: [Option<u8>; 2],
+    /// Byte received by each side from the other side clock
+    received: [Option<u8>; 2],
+}
+
+///One end of a cable between two emulators of the same process
+#[derive(Debug)]
+pub struct Loopback {
+    side: usize,
+    wire: Rc<RefCell<Wire>>,
+}
+
+impl Loopback {
+    ///Both ends of a new cable
+    pub fn pair() -> (Self, Self) {
+        let wire = Rc::new(RefCell::new(Wire::default()));
+        (Self { side: 0, wire: wire.clone() }, Self { side: 1, wire })
+    }
+}
+
+impl SerialLink for Loopback {
+    fn exchange(&mut self, byte: u8) -> u8 {
+        let other = 1 - self.side;
+        let mut wire = self.wire.borrow_mut();
+        match wire.waiting[other].take() {
+            Some(received) => {
+                wire.received[other] = Some(byte);
+                received
+            }
+            // the other side is not listening, our bits are lost
+            None => 0xFF,
+        }
+    }
+
+    fn poll_external(&mut self, byte: u8) -> Option<u8> {
+        let mut wire = self.wire.borrow_mut();
+        let received = wire.received[self.side].take();
+        wire.waiting[self.side] = if received.is_none() { Some(byte) } else { None };
+        received
+    }
+
+    fn stop_listening(&mut self) {
+        let mut wire = self.wire.borrow_mut();
+        wire.waiting[self.side] = None;
+        wire.received[self.side] = None;
+    }
+}
+
+///SB and SC
+#[derive(Debug)]
+pub struct Serial {
+    sb: u8,
+    sc: u8,
+    /// M-cycles spent on the current internal clock transfer
+    cycles: u16,
+    link: Box<dyn SerialLink>,
+}
+
+impl Default for Serial {
+    fn default() -> Self {
+        Self { sb: 0, sc: 0, cycles: 0, link: Box::new(Disconnected) }
+    }
+}
+
+impl Serial {
+    pub fn set_link(&mut self, link: Box<dyn SerialLink>) {
+        self.link = link;
+    }
+
+    ///Advance by one M-cycle, returns true when a transfer completes
+    pub fn tick(&mut self) -> bool {
+        if self.sc & SC_TRANSFER == 0 {
+            return false;
+        }
+
+        if self.sc & SC_INTERNAL_CLOCK != 0 {
+            self.cycles += 1;
+            if self.cycles < TRANSFER_CYCLES {
+                return false;
+            }
+            self.sb = self.link.exchange(self.sb);
+        } else {
+            match self.link.poll_external(self.sb) {
+                Some(byte) => self.sb = byte,
+                None => return false,
+            }
+        }
+
+        self.sc &= !SC_TRANSFER;
+        true
+    }
+
+    pub fn readb(&self, addr: u16) -> u8 {
+        match addr {
+            SB_ADDR => self.sb,
+            SC_ADDR => 0x7E | self.sc,
+            _ => 0xFF,
+        }
+    }
+
+    pub fn writeb(&mut self, addr: u16, byte: u8) {
+        match addr {
+            SB_ADDR => self.sb = byte,
+            SC_ADDR => {
+                self.sc = byte & (SC_TRANSFER | SC_INTERNAL_CLOCK);
+                self.cycles = 0;
+                if self.sc != SC_TRANSFER {
+                    self.link.stop_listening();
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+//MARK: TEST
+
+#[cfg(test)]
+mod test {
+    use crate::mem_bus::io::{
+        SB_ADDR, SC_ADDR,
+        serial::{Capture, Loopback, Serial, TRANSFER_CYCLES},
+    };
+
+    fn send(serial: &mut Serial, byte: u8) {
+        serial.writeb(SB_ADDR, byte);
+        serial.writeb(SC_ADDR, 0x81);
+    }
+
+    #[test]
+    pub fn test_disconnected_transfer() {
+        let mut serial = Serial::default();
+        send(&mut serial, 0x42);
+
+        assert!((1..TRANSFER_CYCLES).all(|_| !serial.tick()));
+        assert_eq!(serial.readb(SC_ADDR), 0xFF);
+        assert!(serial.tick());
+        assert_eq!(serial.readb(SB_ADDR), 0xFF);
+        assert_eq!(serial.readb(SC_ADDR), 0x7F);
+    }
+
+    #[test]
+    pub fn test_capture() {
+        let mut serial = Serial::default();
+        let capture = Capture::default();
+        let output = capture.output();
+        serial.set_link(Box::new(capture));
+
+        for byte in *b"ok" {
+            send(&mut serial, byte);
+            (0..TRANSFER_CYCLES).for_each(|_| {
+                serial.tick();
+            });
+        }
+        assert_eq!(&*output.borrow(), b"ok");
+    }
+
+    #[test]
+    pub fn test_external_clock_waits() {
+        let mut serial = Serial::default();
+        serial.writeb(SB_ADDR, 0x42);
+        serial.writeb(SC_ADDR, 0x80);
+
+        assert!((0..TRANSFER_CYCLES * 2).all(|_| !serial.tick()));
+        assert_eq!(serial.readb(SC_ADDR), 0xFE);
+    }
+
+    #[test]
+    pub fn test_loopback() {
+        let (link_a, link_b) = Loopback::pair();
+        let (mut master, mut slave) = (Serial::default(), Serial::default());
+        master.set_link(Box::new(link_a));
+        slave.set_link(Box::new(link_b));
+
+        slave.writeb(SB_ADDR, 0x22);
+        slave.writeb(SC_ADDR, 0x80);
+        send(&mut master, 0x11);
+
+        let (mut master_done, mut slave_done) = (false, false);
+        for _ in 0..TRANSFER_CYCLES + 1 {
+            slave_done |= slave.tick();
+            master_done |= master.tick();
+        }
+        slave_done |= slave.tick();
+
+        assert!(master_done && slave_done);
+        assert_eq!(master.readb(SB_ADDR), 0x22);
+        assert_eq!(slave.readb(SB_ADDR), 0x11);
+    }
+
+    #[test]
+    pub fn test_loopback_cancelled_transfer() {
+        let (link_a, link_b) = Loopback::pair();
+        let (mut master, mut slave) = (Serial::default(), Serial::default());
+        master.set_link(Box::new(link_a));
+        slave.set_link(Box::new(link_b));
+
+        slave.writeb(SB_ADDR, 0x22);
+        slave.writeb(SC_ADDR, 0x80);
+        slave.tick();
+        slave.writeb(SC_ADDR, 0x00);
+
+        // the slave is no longer listening, its old byte must not be sent
+        send(&mut master, 0x11);
+        assert!((0..TRANSFER_CYCLES).any(|_| master.tick()));
+        assert_eq!(master.readb(SB_ADDR), 0xFF);
+        assert!(!slave.tick());
+        assert_eq!(slave.readb(SB_ADDR), 0x22);
+    }
+}
@@ -0,0 +1,212 @@
+use crate::mem_bus::io::{DIV_ADDR, TAC_ADDR, TIMA_ADDR, TMA_ADDR};
+
+const TAC_ENABLE_MASK: u8 = 0b100;
+const TAC_CLOCK_MASK: u8 = 0b011;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Reload {
+    Idle,
+    /// TIMA overflowed this M-cycle and reads 0x00, TMA is loaded on the next one
+    Pending,
+    /// TMA was just loaded, writes to TIMA are ignored and writes to TMA go through
+    Reloading,
+}
+
+///DIV, TIMA, TMA and TAC, driven by the internal 16 bits divider
+#[derive(Debug)]
+pub struct Timer {
+    /// Incremented every T-cycle, DIV is its upper byte
+    counter: u16,
+    tima: u8,
+    tma: u8,
+    tac: u8,
+    reload: Reload,
+}
+
+impl Default for Timer {
+    fn default() -> Self {
+        Self {
+            counter: 0,
+            tima: 0,
+            tma: 0,
+            tac: 0,
+            reload: Reload::Idle,
+        }
+    }
+}
+
+impl Timer {
+    ///Bit of the divider watched by TIMA for the frequency selected in TAC
+    const fn watched_bit(&self) -> u16 {
+        match self.tac & TAC_CLOCK_MASK {
+            0b00 => 9, // 4096 Hz
+            0b01 => 3, // 262144 Hz
+            0b10 => 5, // 65536 Hz
+            _ => 7,    // 16384 Hz
+        }
+    }
+
+    ///Input of the falling edge detector that increments TIMA
+    const fn signal(&self) -> bool {
+        self.tac & TAC_ENABLE_MASK != 0 && (self.counter >> self.watched_bit()) & 1 != 0
+    }
+
+    fn increment_tima(&mut self) {
+        let (tima, overflow) = self.tima.overflowing_add(1);
+        self.tima = tima;
+        if overflow {
+            self.reload = Reload::Pending;
+        }
+    }
+
+    ///Increment TIMA if the signal went from high to low
+    fn detect_falling_edge(&mut self, old_signal: bool) {
+        if old_signal && !self.signal() {
+            self.increment_tima();
+        }
+    }
+
+    ///Advance by one M-cycle, returns true when the timer interrupt is requested
+    pub fn tick(&mut self) -> bool {
+        let interrupt = match self.reload {
+            Reload::Idle => false,
+            Reload::Pending => {
+                self.tima = self.tma;
+                self.reload = Reload::Reloading;
+                true
+            }
+            Reload::Reloading => {
+                self.reload = Reload::Idle;
+                false
+            }
+        };
+
+        let old_signal = self.signal();
+        self.counter = self.counter.wrapping_add(4);
+        self.detect_falling_edge(old_signal);
+
+        interrupt
+    }
+
+    pub fn readb(&self, addr: u16) -> u8 {
+        match addr {
+            DIV_ADDR => (self.counter >> 8) as u8,
+            TIMA_ADDR => self.tima,
+            TMA_ADDR => self.tma,
+            TAC_ADDR => 0xF8 | self.tac,
+            _ => 0xFF,
+        }
+    }
+
+    pub fn writeb(&mut self, addr: u16, byte: u8) {
+        match addr {
+            // resetting the divider can make the watched bit fall
+            DIV_ADDR => {
+                let old_signal = self.signal();
+                self.counter = 0;
+                self.detect_falling_edge(old_signal);
+            }
+            TIMA_ADDR => match self.reload {
+                // writing during the overflow cycle cancels the reload
+                Reload::Pending => {
+                    self.tima = byte;
+                    self.reload = Reload::Idle;
+                }
+                Reload::Reloading => (),
+                Reload::Idle => self.tima = byte,
+            },
+            TMA_ADDR => {
+                self.tma = byte;
+                if self.reload == Reload::Reloading {
+                    self.tima = byte;
+                }
+            }
+            TAC_ADDR => {
+                let old_signal = self.signal();
+                self.tac = byte & (TAC_ENABLE_MASK | TAC_CLOCK_MASK);
+                self.detect_falling_edge(old_signal);
+            }
+            _ => (),
+        }
+    }
+}
+
+//MARK: TEST
+
+#[cfg(test)]
+mod test {
+    use crate::mem_bus::io::{DIV_ADDR, TAC_ADDR, TIMA_ADDR, TMA_ADDR, timer::Timer};
+
+    fn tick_n(timer: &mut Timer, n: usize) -> usize {
+        (0..n).filter(|_| timer.tick()).count()
+    }
+
+    #[test]
+    pub fn test_div() {
+        let mut timer = Timer::default();
+
+        tick_n(&mut timer, 64);
+        assert_eq!(timer.readb(DIV_ADDR), 1);
+
+        timer.writeb(DIV_ADDR, 0x42);
+        assert_eq!(timer.readb(DIV_ADDR), 0);
+    }
+
+    #[test]
+    pub fn test_tima_frequency() {
+        let mut timer = Timer::default();
+        timer.writeb(TAC_ADDR, 0b101); // every 4 M-cycles
+
+        tick_n(&mut timer, 4 * 10);
+        assert_eq!(timer.readb(TIMA_ADDR), 10);
+    }
+
+    #[test]
+    pub fn test_overflow_delayed_reload() {
+        let mut timer = Timer::default();
+        timer.writeb(TMA_ADDR, 0x80);
+        timer.writeb(TIMA_ADDR, 0xFF);
+        timer.writeb(TAC_ADDR, 0b101);
+
+        assert_eq!(tick_n(&mut timer, 4), 0);
+        // reads 0x00 for one M-cycle before the reload
+        assert_eq!(timer.readb(TIMA_ADDR), 0x00);
+        assert!(timer.tick());
+        assert_eq!(timer.readb(TIMA_ADDR), 0x80);
+    }
+
+    #[test]
+    pub fn test_write_cancels_reload() {
+        let mut timer = Timer::default();
+        timer.writeb(TMA_ADDR, 0x80);
+        timer.writeb(TIMA_ADDR, 0xFF);
+        timer.writeb(TAC_ADDR, 0b101);
+
+        tick_n(&mut timer, 4);
+        timer.writeb(TIMA_ADDR, 0x12);
+        assert!(!timer.tick());
+        assert_eq!(timer.readb(TIMA_ADDR), 0x12);
+    }
+
+    #[test]
+    pub fn test_div_write_falling_edge() {
+        let mut timer = Timer::default();
+        timer.writeb(TAC_ADDR, 0b101); // watches bit 3
+
+        tick_n(&mut timer, 2); // counter = 8, bit 3 high
+        assert_eq!(timer.readb(TIMA_ADDR), 0);
+        timer.writeb(DIV_ADDR, 0);
+        assert_eq!(timer.readb(TIMA_ADDR), 1);
+    }
+
+    #[test]
+    pub fn test_tac_write_falling_edge() {
+        let mut timer = Timer::default();
+        timer.writeb(TAC_ADDR, 0b101);
+
+        tick_n(&mut timer, 2);
+        // disabling the timer while the watched bit is high increments TIMA
+        timer.writeb(TAC_ADDR, 0b001);
+        assert_eq!(timer.readb(TIMA_ADDR), 1);
+    }
+}
@@ -0,0 +1,198 @@
+use crate::mem_bus::{
+    cartridge::{ROM_BANK_SIZE, header::has_nintendo_logo},
+    mbc::{ram_offset, rom_offset, store},
+};
+
+#[derive(Debug)]
+pub struct Mbc1 {
+    rom_banks: usize,
+    ram_banks: usize,
+    multicart: bool,
+
+    ram_enabled: bool,
+    bank1: u8, // 0x2000 -> 0x3FFF, 5 bits
+    bank2: u8, // 0x4000 -> 0x5FFF, 2 bits
+    mode: bool, // 0x6000 -> 0x7FFF
+}
+
+impl Mbc1 {
+    pub fn new(rom_banks: usize, ram_banks: usize, multicart: bool) -> Self {
+        Self {
+            rom_banks,
+            ram_banks,
+            multicart,
+            ram_enabled: false,
+            bank1: 1,
+            bank2: 0,
+            mode: false,
+        }
+    }
+
+    ///MBC1M carts are 1 MiB roms with a game (and so a logo) every 16 banks
+    pub fn is_multicart(rom: &[u8]) -> bool {
+        const GAME_SIZE: usize = 0x10 * ROM_BANK_SIZE;
+        if rom.len() != 0x40 * ROM_BANK_SIZE {
+            return false;
+        }
+
+        let games = (0..4).filter(|game| has_nintendo_logo(rom, game * GAME_SIZE)).count();
+
+        games > 1
+    }
+
+    ///Number of bits of bank1 that are wired to the rom
+    const fn bank1_bits(&self) -> u8 {
+        if self.multicart { 4 } else { 5 }
+    }
+
+    ///Bank mapped at 0x0000 -> 0x3FFF, only non zero in mode 1
+    fn low_bank(&self) -> usize {
+        if self.mode {
+            ((self.bank2 as usize) << self.bank1_bits()) & (self.rom_banks - 1)
+        } else {
+            0
+        }
+    }
+
+    ///Bank mapped at 0x4000 -> 0x7FFF
+    pub fn rom_bank(&self) -> usize {
+        let bank1 = self.bank1 & ((1 << self.bank1_bits()) - 1);
+        let bank = ((self.bank2 as usize) << self.bank1_bits()) | bank1 as usize;
+        bank & (self.rom_banks - 1)
+    }
+
+    fn ram_bank(&self) -> usize {
+        if self.mode && self.ram_banks > 0 {
+            self.bank2 as usize & (self.ram_banks - 1)
+        } else {
+            0
+        }
+    }
+
+    pub fn read_rom(&self, rom: &[u8], addr: u16) -> u8 {
+        match addr {
+            0x0000..=0x3FFF => rom[rom_offset(self.low_bank(), addr)],
+            _ => rom[rom_offset(self.rom_bank(), addr)],
+        }
+    }
+
+    pub fn write_rom(&mut self, addr: u16, byte: u8) {
+        match addr {
+            0x0000..=0x1FFF => self.ram_enabled = byte & 0x0F == 0x0A,
+            // The zero check is done on the full 5 bits, even on a multicart
+            0x2000..=0x3FFF => self.bank1 = (byte & 0x1F).max(1),
+            0x4000..=0x5FFF => self.bank2 = byte & 0b11,
+            _ => self.mode = byte & 0b1 != 0,
+        }
+    }
+
+    pub fn read_ram(&self, ram: &[u8], addr: u16) -> u8 {
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+        *ram.get(ram_offset(self.ram_bank(), addr)).unwrap_or(&0xFF)
+    }
+
+    pub fn write_ram(&mut self, ram: &mut [u8], addr: u16, byte: u8) -> bool {
+        self.ram_enabled && store(ram.get_mut(ram_offset(self.ram_bank(), addr)), byte)
+    }
+}
+
+//MARK: TEST
+
+#[cfg(test)]
+mod test {
+    use crate::mem_bus::{
+        cartridge::{ROM_BANK_SIZE, header::NINTENDO_LOGO},
+        mbc::Mbc1,
+    };
+
+    /// rom where every byte holds its bank number
+    fn make_rom(banks: usize) -> Vec<u8> {
+        (0..banks * ROM_BANK_SIZE).map(|i| (i / ROM_BANK_SIZE) as u8).collect()
+    }
+
+    #[test]
+    pub fn test_rom_banking() {
+        let rom = make_rom(128);
+        let mut mbc = Mbc1::new(128, 0, false);
+
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 1);
+
+        mbc.write_rom(0x2000, 0x05);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 5);
+
+        // bank 0 is remapped to bank 1
+        mbc.write_rom(0x2000, 0x00);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 1);
+
+        // bank 0x20 can't be selected in 0x4000 -> 0x7FFF
+        mbc.write_rom(0x4000, 0x01);
+        mbc.write_rom(0x2000, 0x00);
+        assert_eq!(mbc.read_rom(&rom, 0x7FFF), 0x21);
+
+        // mode 1 also maps bank2 in 0x0000 -> 0x3FFF
+        assert_eq!(mbc.read_rom(&rom, 0x0000), 0);
+        mbc.write_rom(0x6000, 0x01);
+        assert_eq!(mbc.read_rom(&rom, 0x0000), 0x20);
+    }
+
+    #[test]
+    pub fn test_rom_bank_masking() {
+        let rom = make_rom(4);
+        let mut mbc = Mbc1::new(4, 0, false);
+
+        mbc.write_rom(0x2000, 0x06);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 2);
+
+        // the zero check happens before masking
+        mbc.write_rom(0x2000, 0x04);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0);
+    }
+
+    #[test]
+    pub fn test_ram_banking() {
+        let mut ram = vec![0; 0x8000];
+        let mut mbc = Mbc1::new(4, 4, false);
+
+        mbc.write_ram(&mut ram, 0xA000, 0x42);
+        assert_eq!(mbc.read_ram(&ram, 0xA000), 0xFF);
+
+        mbc.write_rom(0x0000, 0x0A);
+        mbc.write_ram(&mut ram, 0xA000, 0x42);
+        assert_eq!(mbc.read_ram(&ram, 0xA000), 0x42);
+
+        // the ram bank is only used in mode 1
+        mbc.write_rom(0x4000, 0x02);
+        assert_eq!(mbc.read_ram(&ram, 0xA000), 0x42);
+        mbc.write_rom(0x6000, 0x01);
+        assert_eq!(mbc.read_ram(&ram, 0xA000), 0x00);
+        mbc.write_ram(&mut ram, 0xA000, 0x24);
+        assert_eq!(ram[0x4000], 0x24);
+    }
+
+    #[test]
+    pub fn test_multicart() {
+        // Identical but wrong logos must not be taken for games
+        let mut rom = make_rom(64);
+        for game in 0..4 {
+            let start = game * 0x10 * ROM_BANK_SIZE + 0x0104;
+            rom[start..start + 0x30].copy_from_slice(&[0xCE; 0x30]);
+        }
+        assert!(!Mbc1::is_multicart(&rom));
+
+        for game in 0..4 {
+            let start = game * 0x10 * ROM_BANK_SIZE + 0x0104;
+            rom[start..start + 0x30].copy_from_slice(&NINTENDO_LOGO);
+        }
+        assert!(Mbc1::is_multicart(&rom));
+
+        let mut mbc = Mbc1::new(64, 0, true);
+        mbc.write_rom(0x4000, 0x01);
+        mbc.write_rom(0x2000, 0x12);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x12);
+
+        mbc.write_rom(0x6000, 0x01);
+        assert_eq!(mbc.read_rom(&rom, 0x0000), 0x10);
+    }
+}
@@ -0,0 +1,94 @@
+use crate::mem_bus::mbc::{rom_offset, store};
+
+/// Built-in ram of 512 half bytes
+pub const MBC2_RAM_SIZE: usize = 0x200;
+
+#[derive(Debug)]
+pub struct Mbc2 {
+    rom_banks: usize,
+
+    ram_enabled: bool,
+    rom_bank: u8, // 4 bits
+}
+
+impl Mbc2 {
+    pub fn new(rom_banks: usize) -> Self {
+        Self {
+            rom_banks,
+            ram_enabled: false,
+            rom_bank: 1,
+        }
+    }
+
+    pub fn rom_bank(&self) -> usize {
+        self.rom_bank as usize & (self.rom_banks - 1)
+    }
+
+    pub fn read_rom(&self, rom: &[u8], addr: u16) -> u8 {
+        match addr {
+            0x0000..=0x3FFF => rom[addr as usize],
+            _ => rom[rom_offset(self.rom_bank(), addr)],
+        }
+    }
+
+    ///Only 0x0000 -> 0x3FFF is wired, the register is selected by the address bit 8
+    pub fn write_rom(&mut self, addr: u16, byte: u8) {
+        match addr {
+            0x0000..=0x3FFF if addr & 0x0100 == 0 => self.ram_enabled = byte & 0x0F == 0x0A,
+            0x0000..=0x3FFF => self.rom_bank = (byte & 0x0F).max(1),
+            _ => (),
+        }
+    }
+
+    ///Only the lower nibble exists, the upper one reads as 1s
+    pub fn read_ram(&self, ram: &[u8], addr: u16) -> u8 {
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+        0xF0 | ram[addr as usize & (MBC2_RAM_SIZE - 1)]
+    }
+
+    pub fn write_ram(&mut self, ram: &mut [u8], addr: u16, byte: u8) -> bool {
+        self.ram_enabled && store(ram.get_mut(addr as usize & (MBC2_RAM_SIZE - 1)), byte & 0x0F)
+    }
+}
+
+//MARK: TEST
+
+#[cfg(test)]
+mod test {
+    use crate::mem_bus::{
+        cartridge::ROM_BANK_SIZE,
+        mbc::{Mbc2, mbc2::MBC2_RAM_SIZE},
+    };
+
+    #[test]
+    pub fn test_register_select() {
+        let rom: Vec<u8> = (0..16 * ROM_BANK_SIZE).map(|i| (i / ROM_BANK_SIZE) as u8).collect();
+        let mut mbc = Mbc2::new(16);
+
+        // A8 clear: ram enable, the bank is untouched
+        mbc.write_rom(0x0000, 0x05);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 1);
+
+        mbc.write_rom(0x2100, 0x05);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 5);
+
+        mbc.write_rom(0x0100, 0x00);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 1);
+    }
+
+    #[test]
+    pub fn test_half_byte_ram() {
+        let mut ram = vec![0; MBC2_RAM_SIZE];
+        let mut mbc = Mbc2::new(2);
+
+        mbc.write_rom(0x0000, 0x0A);
+        mbc.write_ram(&mut ram, 0xA000, 0xAB);
+        assert_eq!(mbc.read_ram(&ram, 0xA000), 0xFB);
+
+        // the 512 half bytes are echoed in the whole area
+        assert_eq!(mbc.read_ram(&ram, 0xA200), 0xFB);
+        assert_eq!(mbc.read_ram(&ram, 0xBE00), 0xFB);
+    }
+}
@@ -0,0 +1,142 @@
+use crate::mem_bus::mbc::{
+    ram_offset, rom_offset, store,
+    rtc::Rtc,
+};
+
+#[derive(Debug)]
+pub struct Mbc3 {
+    rom_banks: usize,
+    ram_banks: usize,
+
+    ram_timer_enabled: bool,
+    rom_bank: u8,   // 0x2000 -> 0x3FFF, 7 bits
+    ram_select: u8, // 0x4000 -> 0x5FFF, ram bank 0x00 -> 0x07 or rtc register 0x08 -> 0x0C
+    rtc: Option<Rtc>,
+}
+
+impl Mbc3 {
+    pub fn new(rom_banks: usize, ram_banks: usize, rtc: Option<Rtc>) -> Self {
+        Self {
+            rom_banks,
+            ram_banks,
+            ram_timer_enabled: false,
+            rom_bank: 1,
+            ram_select: 0,
+            rtc,
+        }
+    }
+
+    pub fn rom_bank(&self) -> usize {
+        self.rom_bank as usize & (self.rom_banks - 1)
+    }
+
+    pub fn rtc(&self) -> Option<&Rtc> {
+        self.rtc.as_ref()
+    }
+
+    pub fn rtc_mut(&mut self) -> Option<&mut Rtc> {
+        self.rtc.as_mut()
+    }
+
+    pub fn read_rom(&self, rom: &[u8], addr: u16) -> u8 {
+        match addr {
+            0x0000..=0x3FFF => rom[addr as usize],
+            _ => rom[rom_offset(self.rom_bank(), addr)],
+        }
+    }
+
+    pub fn write_rom(&mut self, addr: u16, byte: u8) {
+        match addr {
+            0x0000..=0x1FFF => self.ram_timer_enabled = byte & 0x0F == 0x0A,
+            0x2000..=0x3FFF => self.rom_bank = (byte & 0x7F).max(1),
+            0x4000..=0x5FFF => self.ram_select = byte & 0x0F,
+            _ => {
+                if let Some(rtc) = &mut self.rtc {
+                    rtc.write_latch(byte)
+                }
+            }
+        }
+    }
+
+    pub fn read_ram(&self, ram: &[u8], addr: u16) -> u8 {
+        if !self.ram_timer_enabled {
+            return 0xFF;
+        }
+        match (self.ram_select, &self.rtc) {
+            (0x00..=0x07, _) if self.ram_banks > 0 => {
+                let bank = self.ram_select as usize & (self.ram_banks - 1);
+                *ram.get(ram_offset(bank, addr)).unwrap_or(&0xFF)
+            }
+            (0x08..=0x0C, Some(rtc)) => rtc.read(self.ram_select),
+            _ => 0xFF,
+        }
+    }
+
+    pub fn write_ram(&mut self, ram: &mut [u8], addr: u16, byte: u8) -> bool {
+        if !self.ram_timer_enabled {
+            return false;
+        }
+        match (self.ram_select, &mut self.rtc) {
+            (0x00..=0x07, _) if self.ram_banks > 0 => {
+                let bank = self.ram_select as usize & (self.ram_banks - 1);
+                store(ram.get_mut(ram_offset(bank, addr)), byte)
+            }
+            // the clock registers are saved along the ram
+            (0x08..=0x0C, Some(rtc)) => {
+                rtc.write(self.ram_select, byte);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+//MARK: TEST
+
+#[cfg(test)]
+mod test {
+    use std::rc::Rc;
+
+    use crate::mem_bus::{
+        cartridge::ROM_BANK_SIZE,
+        mbc::{Mbc3, rtc::{Rtc, test::FakeClock}},
+    };
+
+    #[test]
+    pub fn test_rom_and_ram_banking() {
+        let rom: Vec<u8> = (0..128 * ROM_BANK_SIZE).map(|i| (i / ROM_BANK_SIZE) as u8).collect();
+        let mut ram = vec![0; 0x8000];
+        let mut mbc = Mbc3::new(128, 4, None);
+
+        mbc.write_rom(0x2000, 0x00);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 1);
+        mbc.write_rom(0x2000, 0x7F);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x7F);
+
+        mbc.write_rom(0x0000, 0x0A);
+        mbc.write_rom(0x4000, 0x03);
+        mbc.write_ram(&mut ram, 0xA001, 0x42);
+        assert_eq!(ram[0x6001], 0x42);
+        assert_eq!(mbc.read_ram(&ram, 0xA001), 0x42);
+    }
+
+    #[test]
+    pub fn test_rtc_registers() {
+        let clock = FakeClock::default();
+        let mut mbc = Mbc3::new(2, 1, Some(Rtc::new(Rc::new(clock.clone()))));
+        let mut ram = vec![0; 0x2000];
+
+        mbc.write_rom(0x0000, 0x0A);
+        mbc.write_rom(0x4000, 0x09);
+        mbc.write_ram(&mut ram, 0xA000, 10);
+
+        clock.advance(125);
+        mbc.write_rom(0x6000, 0x00);
+        mbc.write_rom(0x6000, 0x01);
+
+        assert_eq!(mbc.read_ram(&ram, 0xA000), 12);
+        mbc.write_rom(0x4000, 0x08);
+        assert_eq!(mbc.read_ram(&ram, 0xA000), 5);
+        assert_eq!(ram[0], 0);
+    }
+}
@@ -0,0 +1,117 @@
+use crate::mem_bus::mbc::{ram_offset, rom_offset, store};
+
+/// On rumble carts, bit 3 of the ram bank register drives the motor
+const RUMBLE_MASK: u8 = 0b1000;
+
+#[derive(Debug)]
+pub struct Mbc5 {
+    rom_banks: usize,
+    ram_banks: usize,
+    has_rumble: bool,
+
+    ram_enabled: bool,
+    rom_bank: u16, // 9 bits, 0x2000 -> 0x2FFF low byte, 0x3000 -> 0x3FFF bit 8
+    ram_bank: u8,  // 4 bits
+    rumble: bool,
+}
+
+impl Mbc5 {
+    pub fn new(rom_banks: usize, ram_banks: usize, has_rumble: bool) -> Self {
+        Self {
+            rom_banks,
+            ram_banks,
+            has_rumble,
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            rumble: false,
+        }
+    }
+
+    ///Unlike the other mbcs, bank 0 can be mapped at 0x4000 -> 0x7FFF
+    pub fn rom_bank(&self) -> usize {
+        self.rom_bank as usize & (self.rom_banks - 1)
+    }
+
+    pub fn is_rumbling(&self) -> bool {
+        self.rumble
+    }
+
+    pub fn read_rom(&self, rom: &[u8], addr: u16) -> u8 {
+        match addr {
+            0x0000..=0x3FFF => rom[addr as usize],
+            _ => rom[rom_offset(self.rom_bank(), addr)],
+        }
+    }
+
+    pub fn write_rom(&mut self, addr: u16, byte: u8) {
+        match addr {
+            0x0000..=0x1FFF => self.ram_enabled = byte & 0x0F == 0x0A,
+            0x2000..=0x2FFF => self.rom_bank = (self.rom_bank & 0x100) | byte as u16,
+            0x3000..=0x3FFF => self.rom_bank = (self.rom_bank & 0xFF) | (((byte & 0b1) as u16) << 8),
+            0x4000..=0x5FFF => {
+                if self.has_rumble {
+                    self.rumble = byte & RUMBLE_MASK != 0;
+                    self.ram_bank = byte & 0b0111;
+                } else {
+                    self.ram_bank = byte & 0x0F;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn ram_bank(&self) -> usize {
+        if self.ram_banks == 0 {
+            0
+        } else {
+            self.ram_bank as usize & (self.ram_banks - 1)
+        }
+    }
+
+    pub fn read_ram(&self, ram: &[u8], addr: u16) -> u8 {
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+        *ram.get(ram_offset(self.ram_bank(), addr)).unwrap_or(&0xFF)
+    }
+
+    pub fn write_ram(&mut self, ram: &mut [u8], addr: u16, byte: u8) -> bool {
+        self.ram_enabled && store(ram.get_mut(ram_offset(self.ram_bank(), addr)), byte)
+    }
+}
+
+//MARK: TEST
+
+#[cfg(test)]
+mod test {
+    use crate::mem_bus::{cartridge::ROM_BANK_SIZE, mbc::Mbc5};
+
+    #[test]
+    pub fn test_9_bits_rom_bank() {
+        let rom: Vec<u8> = (0..512 * ROM_BANK_SIZE).map(|i| (i / ROM_BANK_SIZE) as u8).collect();
+        let mut mbc = Mbc5::new(512, 0, false);
+
+        mbc.write_rom(0x2000, 0x00);
+        assert_eq!(mbc.rom_bank(), 0);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0);
+
+        mbc.write_rom(0x2000, 0x23);
+        mbc.write_rom(0x3000, 0x01);
+        assert_eq!(mbc.rom_bank(), 0x123);
+        assert_eq!(mbc.read_rom(&rom, 0x4000), 0x23);
+    }
+
+    #[test]
+    pub fn test_rumble() {
+        let mut ram = vec![0; 0x2000 * 16];
+        let mut mbc = Mbc5::new(2, 16, true);
+
+        mbc.write_rom(0x0000, 0x0A);
+        mbc.write_rom(0x4000, 0x0A);
+        assert!(mbc.is_rumbling());
+
+        mbc.write_ram(&mut ram, 0xA000, 0x42);
+        assert_eq!(ram[0x2000 * 2], 0x42);
+    }
+}
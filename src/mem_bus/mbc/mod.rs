@@ -0,0 +1,139 @@
+use std::rc::Rc;
+
+use crate::mem_bus::{
+    cartridge::{CartridgeHeader, RAM_BANK_SIZE, ROM_BANK_SIZE, header::Mapper},
+    mbc::rtc::{Clock, Rtc},
+};
+
+mod mbc1;
+pub mod mbc2;
+mod mbc3;
+mod mbc5;
+pub mod rtc;
+
+pub use mbc1::Mbc1;
+pub use mbc2::Mbc2;
+pub use mbc3::Mbc3;
+pub use mbc5::Mbc5;
+
+#[derive(Debug)]
+pub enum Mbc {
+    RomOnly,
+    Mbc1(Mbc1),
+    Mbc2(Mbc2),
+    Mbc3(Mbc3),
+    Mbc5(Mbc5),
+}
+
+impl Mbc {
+    ///The clock is only used by cartridges with a timer
+    pub fn new(header: Option<&CartridgeHeader>, rom: &[u8], ram_size: usize, clock: Rc<dyn Clock>) -> Self {
+        let rom_banks = rom.len() / ROM_BANK_SIZE;
+        let ram_banks = ram_size.div_ceil(RAM_BANK_SIZE);
+
+        let Some(header) = header else {
+            return Mbc::RomOnly;
+        };
+
+        match header.cartridge_type.mapper() {
+            Mapper::Mbc1 => Mbc::Mbc1(Mbc1::new(rom_banks, ram_banks, Mbc1::is_multicart(rom))),
+            Mapper::Mbc2 => Mbc::Mbc2(Mbc2::new(rom_banks)),
+            Mapper::Mbc3 => {
+                let rtc = header.cartridge_type.has_timer().then(|| Rtc::new(clock));
+                Mbc::Mbc3(Mbc3::new(rom_banks, ram_banks, rtc))
+            }
+            Mapper::Mbc5 => Mbc::Mbc5(Mbc5::new(rom_banks, ram_banks, header.cartridge_type.has_rumble())),
+            _ => Mbc::RomOnly,
+        }
+    }
+
+    pub fn is_rumbling(&self) -> bool {
+        match self {
+            Mbc::Mbc5(mbc) => mbc.is_rumbling(),
+            _ => false,
+        }
+    }
+
+    pub fn rtc_mut(&mut self) -> Option<&mut Rtc> {
+        match self {
+            Mbc::Mbc3(mbc) => mbc.rtc_mut(),
+            _ => None,
+        }
+    }
+
+    ///Bank currently mapped at 0x4000 -> 0x7FFF
+    pub fn rom_bank(&self) -> usize {
+        match self {
+            Mbc::RomOnly => 1,
+            Mbc::Mbc1(mbc) => mbc.rom_bank(),
+            Mbc::Mbc2(mbc) => mbc.rom_bank(),
+            Mbc::Mbc3(mbc) => mbc.rom_bank(),
+            Mbc::Mbc5(mbc) => mbc.rom_bank(),
+        }
+    }
+
+    pub fn read_rom(&self, rom: &[u8], addr: u16) -> u8 {
+        match self {
+            Mbc::RomOnly => rom[addr as usize],
+            Mbc::Mbc1(mbc) => mbc.read_rom(rom, addr),
+            Mbc::Mbc2(mbc) => mbc.read_rom(rom, addr),
+            Mbc::Mbc3(mbc) => mbc.read_rom(rom, addr),
+            Mbc::Mbc5(mbc) => mbc.read_rom(rom, addr),
+        }
+    }
+
+    pub fn write_rom(&mut self, addr: u16, byte: u8) {
+        match self {
+            Mbc::RomOnly => (),
+            Mbc::Mbc1(mbc) => mbc.write_rom(addr, byte),
+            Mbc::Mbc2(mbc) => mbc.write_rom(addr, byte),
+            Mbc::Mbc3(mbc) => mbc.write_rom(addr, byte),
+            Mbc::Mbc5(mbc) => mbc.write_rom(addr, byte),
+        }
+    }
+
+    pub fn read_ram(&self, ram: &[u8], addr: u16) -> u8 {
+        match self {
+            Mbc::RomOnly => *ram.get((addr - 0xA000) as usize).unwrap_or(&0xFF),
+            Mbc::Mbc1(mbc) => mbc.read_ram(ram, addr),
+            Mbc::Mbc2(mbc) => mbc.read_ram(ram, addr),
+            Mbc::Mbc3(mbc) => mbc.read_ram(ram, addr),
+            Mbc::Mbc5(mbc) => mbc.read_ram(ram, addr),
+        }
+    }
+
+    ///Returns whether the battery backed data changed
+    pub fn write_ram(&mut self, ram: &mut [u8], addr: u16, byte: u8) -> bool {
+        match self {
+            Mbc::RomOnly => store(ram.get_mut((addr - 0xA000) as usize), byte),
+            Mbc::Mbc1(mbc) => mbc.write_ram(ram, addr, byte),
+            Mbc::Mbc2(mbc) => mbc.write_ram(ram, addr, byte),
+            Mbc::Mbc3(mbc) => mbc.write_ram(ram, addr, byte),
+            Mbc::Mbc5(mbc) => mbc.write_ram(ram, addr, byte),
+        }
+    }
+}
+
+///Write to a ram cell if there is one, returns whether its value changed
+#[inline]
+fn store(cell: Option<&mut u8>, byte: u8) -> bool {
+    match cell {
+        Some(cell) if *cell != byte => {
+            *cell = byte;
+            true
+        }
+        _ => false,
+    }
+}
+
+///Offset of addr inside a 16 KiB rom bank
+#[inline]
+const fn rom_offset(bank: usize, addr: u16) -> usize {
+    bank * ROM_BANK_SIZE + (addr as usize & (ROM_BANK_SIZE - 1))
+}
+
+///Offset of addr inside a 8 KiB ram bank
+#[inline]
+const fn ram_offset(bank: usize, addr: u16) -> usize {
+    bank * RAM_BANK_SIZE + (addr as usize & (RAM_BANK_SIZE - 1))
+}
@@ -0,0 +1,389 @@
+use std::{
+    fmt::Debug,
+    rc::Rc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Size of the rtc data appended after the ram in the save files
+pub const RTC_TRAILER_SIZE: usize = 48;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+const MILLIS_PER_SECOND: u64 = 1000;
+const DAY_HIGH_MASK: u8 = 0b0000_0001;
+const HALT_MASK: u8 = 0b0100_0000;
+const DAY_CARRY_MASK: u8 = 0b1000_0000;
+
+///Source of the wall clock time, in milliseconds since the unix epoch
+pub trait Clock: Debug {
+    fn now(&self) -> u64;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0)
+    }
+}
+
+///The five rtc registers, selected with 0x08 -> 0x0C
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RtcRegisters {
+    pub seconds: u8,  // 0x08
+    pub minutes: u8,  // 0x09
+    pub hours: u8,    // 0x0A
+    pub day_low: u8,  // 0x0B
+    pub day_high: u8, // 0x0C, bit 0: day bit 8, bit 6: halt, bit 7: day carry
+}
+
+impl RtcRegisters {
+    pub const fn read(&self, reg: u8) -> u8 {
+        match reg {
+            0x08 => self.seconds,
+            0x09 => self.minutes,
+            0x0A => self.hours,
+            0x0B => self.day_low,
+            0x0C => self.day_high,
+            _ => 0xFF,
+        }
+    }
+
+    pub const fn write(&mut self, reg: u8, byte: u8) {
+        match reg {
+            0x08 => self.seconds = byte & 0x3F,
+            0x09 => self.minutes = byte & 0x3F,
+            0x0A => self.hours = byte & 0x1F,
+            0x0B => self.day_low = byte,
+            0x0C => self.day_high = byte & (DAY_HIGH_MASK | HALT_MASK | DAY_CARRY_MASK),
+            _ => (),
+        }
+    }
+
+    pub const fn days(&self) -> u16 {
+        self.day_low as u16 | (((self.day_high & DAY_HIGH_MASK) as u16) << 8)
+    }
+
+    const fn set_days(&mut self, days: u16) {
+        self.day_low = (days & 0xFF) as u8;
+        self.day_high = (self.day_high & !DAY_HIGH_MASK) | ((days >> 8) as u8 & DAY_HIGH_MASK);
+    }
+
+    pub const fn is_halted(&self) -> bool {
+        self.day_high & HALT_MASK != 0
+    }
+
+    const fn in_range(&self) -> bool {
+        self.seconds < 60 && self.minutes < 60 && self.hours < 24
+    }
+
+    ///Advance by one second, out of range values wrap at their bit width without carrying
+    fn tick(&mut self) {
+        self.seconds = (self.seconds + 1) & 0x3F;
+        if self.seconds != 60 {
+            return;
+        }
+        self.seconds = 0;
+
+        self.minutes = (self.minutes + 1) & 0x3F;
+        if self.minutes != 60 {
+            return;
+        }
+        self.minutes = 0;
+
+        self.hours = (self.hours + 1) & 0x1F;
+        if self.hours != 24 {
+            return;
+        }
+        self.hours = 0;
+
+        self.add_days(1);
+    }
+
+    fn add_days(&mut self, days: u64) {
+        let days = self.days() as u64 + days;
+        if days > 0x1FF {
+            self.day_high |= DAY_CARRY_MASK;
+        }
+        self.set_days((days & 0x1FF) as u16);
+    }
+
+    pub fn advance(&mut self, mut seconds: u64) {
+        while seconds > 0 && !self.in_range() {
+            self.tick();
+            seconds -= 1;
+        }
+        if seconds == 0 {
+            return;
+        }
+
+        let time_of_day = self.seconds as u64 + self.minutes as u64 * 60 + self.hours as u64 * 3600 + seconds;
+        self.add_days(time_of_day / SECONDS_PER_DAY);
+
+        let time_of_day = time_of_day % SECONDS_PER_DAY;
+        self.hours = (time_of_day / 3600) as u8;
+        self.minutes = (time_of_day / 60 % 60) as u8;
+        self.seconds = (time_of_day % 60) as u8;
+    }
+}
+
+///MBC3 real time clock, kept up to date lazily from a wall clock
+#[derive(Debug)]
+pub struct Rtc {
+    live: RtcRegisters,
+    latched: RtcRegisters,
+    latch_armed: bool,
+    last_update: u64,
+    /// Milliseconds counted toward the next second
+    sub_second: u64,
+    clock: Rc<dyn Clock>,
+}
+
+impl Rtc {
+    pub fn new(clock: Rc<dyn Clock>) -> Self {
+        let last_update = clock.now();
+        Self {
+            live: RtcRegisters::default(),
+            latched: RtcRegisters::default(),
+            latch_armed: false,
+            last_update,
+            sub_second: 0,
+            clock,
+        }
+    }
+
+    ///Catch up with the wall clock
+    pub fn update(&mut self) {
+        let now = self.clock.now();
+        let elapsed = now.saturating_sub(self.last_update);
+        self.last_update = now;
+
+        if !self.live.is_halted() {
+            let millis = self.sub_second + elapsed;
+            self.sub_second = millis % MILLIS_PER_SECOND;
+            self.live.advance(millis / MILLIS_PER_SECOND);
+        }
+    }
+
+    pub fn read(&self, reg: u8) -> u8 {
+        self.latched.read(reg)
+    }
+
+    ///Writing the seconds also restarts the current second, like the hardware divider
+    pub fn write(&mut self, reg: u8, byte: u8) {
+        self.update();
+        self.live.write(reg, byte);
+        if reg == 0x08 {
+            self.sub_second = 0;
+        }
+    }
+
+    ///Writing 0x00 then 0x01 copies the live registers into the latched ones
+    pub fn write_latch(&mut self, byte: u8) {
+        if self.latch_armed && byte == 0x01 {
+            self.update();
+            self.latched = self.live;
+        }
+        self.latch_armed = byte == 0x00;
+    }
+
+    pub fn live(&self) -> &RtcRegisters {
+        &self.live
+    }
+
+    pub fn latched(&self) -> &RtcRegisters {
+        &self.latched
+    }
+}
+
+//MARK: Save trailer
+
+impl Rtc {
+    ///Live then latched registers as 32 bits little endian values, then the 64 bits unix timestamp in seconds
+    pub fn save_trailer(&mut self) -> [u8; RTC_TRAILER_SIZE] {
+        self.update();
+
+        let mut trailer = [0; RTC_TRAILER_SIZE];
+        let regs = [self.live, self.latched];
+        let values = regs
+            .iter()
+            .flat_map(|r| [r.seconds, r.minutes, r.hours, r.day_low, r.day_high]);
+        for (i, value) in values.enumerate() {
+            trailer[i * 4..i * 4 + 4].copy_from_slice(&(value as u32).to_le_bytes());
+        }
+        trailer[40..48].copy_from_slice(&(self.last_update / MILLIS_PER_SECOND).to_le_bytes());
+
+        trailer
+    }
+
+    ///Restore the registers from a save trailer, the time elapsed since the save is applied on next update
+    pub fn load_trailer(&mut self, trailer: &[u8; RTC_TRAILER_SIZE]) {
+        let value = |i: usize| {
+            u32::from_le_bytes([trailer[i * 4], trailer[i * 4 + 1], trailer[i * 4 + 2], trailer[i * 4 + 3]]) as u8
+        };
+        for (i, regs) in [&mut self.live, &mut self.latched].into_iter().enumerate() {
+            let base = i * 5;
+            regs.write(0x08, value(base));
+            regs.write(0x09, value(base + 1));
+            regs.write(0x0A, value(base + 2));
+            regs.write(0x0B, value(base + 3));
+            regs.write(0x0C, value(base + 4));
+        }
+
+        let mut timestamp = [0; 8];
+        timestamp.copy_from_slice(&trailer[40..48]);
+        self.last_update = u64::from_le_bytes(timestamp) * MILLIS_PER_SECOND;
+        self.sub_second = 0;
+        self.update();
+    }
+}
+
+//MARK: TEST
+
+#[cfg(test)]
+pub mod test {
+    use std::{cell::Cell, rc::Rc};
+
+    use crate::mem_bus::mbc::rtc::{Clock, Rtc, RtcRegisters};
+
+    #[derive(Debug, Clone, Default)]
+    pub struct FakeClock(pub Rc<Cell<u64>>);
+
+    impl FakeClock {
+        pub fn advance(&self, seconds: u64) {
+            self.advance_millis(seconds * 1000);
+        }
+
+        pub fn advance_millis(&self, millis: u64) {
+            self.0.set(self.0.get() + millis);
+        }
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> u64 {
+            self.0.get()
+        }
+    }
+
+    fn latch(rtc: &mut Rtc) {
+        rtc.write_latch(0x00);
+        rtc.write_latch(0x01);
+    }
+
+    #[test]
+    pub fn test_rtc_counts_time() {
+        let clock = FakeClock::default();
+        let mut rtc = Rtc::new(Rc::new(clock.clone()));
+
+        clock.advance(2 * 86400 + 3 * 3600 + 4 * 60 + 5);
+        latch(&mut rtc);
+        assert_eq!(rtc.read(0x08), 5);
+        assert_eq!(rtc.read(0x09), 4);
+        assert_eq!(rtc.read(0x0A), 3);
+        assert_eq!(rtc.read(0x0B), 2);
+    }
+
+    #[test]
+    pub fn test_latch_needs_zero_then_one() {
+        let clock = FakeClock::default();
+        let mut rtc = Rtc::new(Rc::new(clock.clone()));
+
+        clock.advance(10);
+        rtc.write_latch(0x01);
+        assert_eq!(rtc.read(0x08), 0);
+
+        latch(&mut rtc);
+        clock.advance(10);
+        assert_eq!(rtc.read(0x08), 10);
+    }
+
+    #[test]
+    pub fn test_halt() {
+        let clock = FakeClock::default();
+        let mut rtc = Rtc::new(Rc::new(clock.clone()));
+
+        rtc.write(0x0C, 0x40);
+        clock.advance(100);
+        latch(&mut rtc);
+        assert_eq!(rtc.read(0x08), 0);
+
+        rtc.write(0x0C, 0x00);
+        clock.advance(100);
+        latch(&mut rtc);
+        assert_eq!(rtc.read(0x08), 40);
+        assert_eq!(rtc.read(0x09), 1);
+    }
+
+    #[test]
+    pub fn test_seconds_write_resets_sub_second() {
+        let clock = FakeClock::default();
+        let mut rtc = Rtc::new(Rc::new(clock.clone()));
+
+        clock.advance_millis(700);
+        rtc.write(0x08, 0);
+        clock.advance_millis(700);
+        latch(&mut rtc);
+        assert_eq!(rtc.read(0x08), 0);
+
+        clock.advance_millis(300);
+        latch(&mut rtc);
+        assert_eq!(rtc.read(0x08), 1);
+    }
+
+    #[test]
+    pub fn test_day_overflow() {
+        let mut regs = RtcRegisters::default();
+        regs.write(0x0B, 0xFF);
+        regs.write(0x0C, 0x01);
+        regs.write(0x0A, 23);
+        regs.write(0x09, 59);
+        regs.write(0x08, 59);
+
+        regs.advance(1);
+        assert_eq!(regs.days(), 0);
+        assert_eq!(regs.day_high & 0x80, 0x80);
+        assert_eq!(regs.hours, 0);
+
+        // the carry is sticky
+        regs.advance(86400);
+        assert_eq!(regs.days(), 1);
+        assert_eq!(regs.day_high & 0x80, 0x80);
+    }
+
+    #[test]
+    pub fn test_out_of_range_wraps_without_carry() {
+        let mut regs = RtcRegisters::default();
+        regs.write(0x08, 62);
+
+        regs.advance(2);
+        assert_eq!(regs.seconds, 0);
+        assert_eq!(regs.minutes, 0);
+
+        regs.advance(61);
+        assert_eq!(regs.seconds, 1);
+        assert_eq!(regs.minutes, 1);
+    }
+
+    #[test]
+    pub fn test_trailer_round_trip_catches_up() {
+        let clock = FakeClock::default();
+        clock.advance(1_000_000);
+        let mut rtc = Rtc::new(Rc::new(clock.clone()));
+        rtc.write(0x09, 30);
+        latch(&mut rtc);
+
+        let trailer = rtc.save_trailer();
+
+        clock.advance(3600);
+        let mut restored = Rtc::new(Rc::new(clock.clone()));
+        restored.load_trailer(&trailer);
+        assert_eq!(restored.latched(), rtc.latched());
+
+        latch(&mut restored);
+        assert_eq!(restored.read(0x09), 30);
+        assert_eq!(restored.read(0x0A), 1);
+    }
+}
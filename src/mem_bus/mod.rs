@@ -1,48 +1,329 @@
+use std::rc::Rc;
+
+use crate::{
+    cpu::interrupts::Interrupt,
+    graphics::ppu::{Ppu, Renderer},
+    mem_bus::{
+        cartridge::Cartridge,
+        io::{
+            DMA_ADDR, JOYPAD_ADDR,
+            dma::Dma,
+            joypad::Joypad,
+            serial::{Serial, SerialLink},
+            timer::Timer,
+        },
+        mbc::rtc::SystemClock,
+    },
+    utils::{bytes_to_word, word_to_bytes},
+};
+
+pub mod bus;
+pub mod cartridge;
+pub mod io;
+pub mod mbc;
+
+pub use bus::Bus;
+
+const WRAM_SIZE : usize = 0x2000;
+const IO_SIZE   : usize = 0x80;
+const HRAM_SIZE : usize = 0x7F;
+
 #[derive(Debug)]
 pub struct MemBus {
-    rom : [u8; 0x4000], // 0x0000 -> 0x3FFF
-    //[...]
+    cartridge: Cartridge,   // 0x0000 -> 0x7FFF, 0xA000 -> 0xBFFF
+    ppu : Ppu,              // 0x8000 -> 0x9FFF, 0xFE00 -> 0xFE9F, 0xFF40 -> 0xFF4B
+    wram: [u8; WRAM_SIZE],  // 0xC000 -> 0xDFFF
+                            // 0xE000 -> 0xFDFF echo of 0xC000 -> 0xDDFF
+                            // 0xFEA0 -> 0xFEFF not usable
+    io  : [u8; IO_SIZE],    // 0xFF00 -> 0xFF7F
+    joypad: Joypad,         // 0xFF00
+    serial: Serial,         // 0xFF01 -> 0xFF02
+    timer: Timer,           // 0xFF04 -> 0xFF07
+    dma : Dma,              // 0xFF46
+    hram: [u8; HRAM_SIZE],  // 0xFF80 -> 0xFFFE
     if_flag: u8, // 0xFF0F
     ie_flag: u8, // 0xFFFF
 }
 
 impl MemBus {
+    ///Cartridge timers follow the system clock
     pub fn from_bytes(rom: &[u8])->Self{
-        let rom = core::array::from_fn(|i|*rom.get(i).unwrap_or(&0));
+        Self::from_cartridge(Cartridge::from_bytes(rom, Rc::new(SystemClock)))
+    }
+
+    pub fn from_cartridge(cartridge: Cartridge)->Self{
+        Self {
+            cartridge,
+            ppu: Ppu::default(),
+            wram: [0; WRAM_SIZE],
+            io: [0; IO_SIZE],
+            joypad: Joypad::default(),
+            serial: Serial::default(),
+            timer: Timer::default(),
+            dma: Dma::default(),
+            hram: [0; HRAM_SIZE],
+            if_flag: 0,
+            ie_flag: 0,
+        }
+    }
+
+    ///Replace the ppu by a fresh one using the given renderer
+    pub fn with_renderer(mut self, renderer: Renderer) -> Self {
+        self.ppu = Ppu::new(renderer);
+        self
+    }
+}
+
+impl MemBus {
+    pub fn cartridge(&self) -> &Cartridge {
+        &self.cartridge
+    }
+
+    pub fn cartridge_mut(&mut self) -> &mut Cartridge {
+        &mut self.cartridge
+    }
+
+    pub fn ppu(&self) -> &Ppu {
+        &self.ppu
+    }
+
+    pub fn joypad_mut(&mut self) -> &mut Joypad {
+        &mut self.joypad
+    }
+
+    ///Plug something in the link port, disconnected by default
+    pub fn set_serial_link(&mut self, link: Box<dyn SerialLink>) {
+        self.serial.set_link(link);
+    }
+}
+
+impl Bus for MemBus {
+    fn readb(&self, addr: u16) -> u8 {
+        MemBus::readb(self, addr)
+    }
+
+    fn writeb(&mut self, addr: u16, byte: u8) {
+        MemBus::writeb(self, addr, byte);
+    }
+
+    ///Advance the peripherals by one M-cycle
+    fn tick(&mut self) {
+        if self.joypad.take_interrupt() {
+            self.request_interrupt(Interrupt::Joypad);
+        }
+        if self.timer.tick() {
+            self.request_interrupt(Interrupt::Timer);
+        }
+        if self.serial.tick() {
+            self.request_interrupt(Interrupt::Serial);
+        }
+        self.if_flag |= self.ppu.tick();
+        if let Some((source, offset)) = self.dma.tick() {
+            let byte = self.read_direct(source);
+            self.ppu.write_oam(0xFE00 + offset, byte);
+            self.dma.set_bus_value(byte);
+        }
+    }
+
+    // IF and IE stay reachable during an OAM DMA
+    fn pending_interrupts(&self) -> u8 {
+        MemBus::pending_interrupts(self)
+    }
+
+    fn clear_interrupt(&mut self, interrupt: Interrupt) {
+        MemBus::clear_interrupt(self, interrupt);
+    }
+}
+
+// -- interrupts --
+impl MemBus {
+    pub fn request_interrupt(&mut self, interrupt: Interrupt) {
+        self.if_flag |= interrupt.mask();
+    }
+
+    pub fn clear_interrupt(&mut self, interrupt: Interrupt) {
+        self.if_flag &= !interrupt.mask();
+    }
 
-        Self { rom, if_flag: 0, ie_flag: 0, }
+    ///Interrupts both requested and enabled
+    pub fn pending_interrupts(&self) -> u8 {
+        self.if_flag & self.ie_flag & 0x1F
     }
 }
 
 impl MemBus {
+    ///During an OAM DMA the CPU only reaches HRAM, other reads see the byte being transferred
     pub fn readb(&self, addr: u16) -> u8 {
+        if self.dma.is_active() && !(0xFF80..=0xFFFE).contains(&addr) {
+            return self.dma.bus_value();
+        }
+        self.read_direct(addr)
+    }
+
+    fn read_direct(&self, addr: u16) -> u8 {
         match addr{
-            0x0000..0x4000 => self.rom[addr as usize],
+            0x0000..=0x7FFF => self.cartridge.read_rom(addr),
+            0x8000..=0x9FFF => self.ppu.read_vram(addr),
+            0xA000..=0xBFFF => self.cartridge.read_ram(addr),
+            0xC000..=0xDFFF => self.wram[(addr - 0xC000) as usize],
+            0xE000..=0xFDFF => self.wram[(addr - 0xE000) as usize],
+            0xFE00..=0xFE9F => self.ppu.read_oam(addr),
+            0xFEA0..=0xFEFF => 0x00,
 
-            0xFF0F => self.if_flag,
+            JOYPAD_ADDR => self.joypad.readb(addr),
+            0xFF01..=0xFF02 => self.serial.readb(addr),
+            0xFF04..=0xFF07 => self.timer.readb(addr),
+            0xFF0F => 0xE0 | self.if_flag,
+            DMA_ADDR => self.dma.read(),
+            0xFF40..=0xFF45 | 0xFF47..=0xFF4B => self.ppu.readb(addr),
+            0xFF03..=0xFF7F => self.io[(addr - 0xFF00) as usize],
+            0xFF80..=0xFFFE => self.hram[(addr - 0xFF80) as usize],
             0xFFFF => self.ie_flag,
-
-            _ => 0xFF,
         }
     }
 
     pub fn readw(&self, addr: u16) -> u16 {
+        bytes_to_word(self.readb(addr), self.readb(addr.wrapping_add(1)))
+    }
+
+    ///During an OAM DMA the CPU only reaches HRAM and FF46 to restart the transfer, other writes are lost
+    pub fn writeb(&mut self, addr: u16, byte: u8) {
+        if self.dma.is_active() && !(0xFF80..=0xFFFE).contains(&addr) && addr != DMA_ADDR {
+            return;
+        }
+
         match addr{
-            0x0000..0x3FFF => (self.rom[addr as usize] as u16) | ((self.rom[addr as usize + 1] as u16)<< 8),
-            0x3FFF => self.rom[addr as usize] as u16,
+            0x0000..=0x7FFF => self.cartridge.write_rom(addr, byte),
+            0x8000..=0x9FFF => self.ppu.write_vram(addr, byte),
+            0xA000..=0xBFFF => self.cartridge.write_ram(addr, byte),
+            0xC000..=0xDFFF => self.wram[(addr - 0xC000) as usize] = byte,
+            0xE000..=0xFDFF => self.wram[(addr - 0xE000) as usize] = byte,
+            0xFE00..=0xFE9F => self.ppu.write_oam(addr, byte),
+            0xFEA0..=0xFEFF => (),
+
+            JOYPAD_ADDR => self.joypad.writeb(addr, byte),
+            0xFF01..=0xFF02 => self.serial.writeb(addr, byte),
+            0xFF04..=0xFF07 => self.timer.writeb(addr, byte),
+            0xFF0F => self.if_flag = byte & 0x1F,
+            DMA_ADDR => self.dma.write(byte),
+            0xFF40..=0xFF45 | 0xFF47..=0xFF4B => self.ppu.writeb(addr, byte),
+            0xFF03..=0xFF7F => self.io[(addr - 0xFF00) as usize] = byte,
+            0xFF80..=0xFFFE => self.hram[(addr - 0xFF80) as usize] = byte,
+            0xFFFF => self.ie_flag = byte,
+        }
+    }
+
+    pub fn writew(&mut self, addr: u16, word: u16) {
+        let (low, high) = word_to_bytes(word);
+        self.writeb(addr, low);
+        self.writeb(addr.wrapping_add(1), high);
+    }
+}
+
+//MARK: TEST
+
+#[cfg(test)]
+mod test {
+    use crate::mem_bus::{Bus, MemBus};
+
+    fn tick_n(bus: &mut MemBus, n: usize) {
+        (0..n).for_each(|_| bus.tick());
+    }
 
-            0xFF0F => self.if_flag as u16,
-            0xFFFF => self.ie_flag as u16,
+    #[test]
+    pub fn test_echo_ram_mirror() {
+        let mut bus = MemBus::from_bytes(&[]);
 
-            _ => 0xFFFF,
+        bus.writeb(0xC123, 0x42);
+        assert_eq!(bus.readb(0xE123), 0x42);
+
+        bus.writeb(0xFDFF, 0x24);
+        assert_eq!(bus.readb(0xDDFF), 0x24);
+    }
+
+    #[test]
+    pub fn test_rom_is_read_only() {
+        let mut bus = MemBus::from_bytes(&[0x12, 0x34]);
+
+        bus.writeb(0x0000, 0xFF);
+        assert_eq!(bus.readb(0x0000), 0x12);
+        assert_eq!(bus.readw(0x0000), 0x3412);
+    }
+
+    #[test]
+    pub fn test_word_access() {
+        let mut bus = MemBus::from_bytes(&[]);
+
+        bus.writew(0xFFFD, 0xBEEF);
+        assert_eq!(bus.readb(0xFFFD), 0xEF);
+        assert_eq!(bus.readb(0xFFFE), 0xBE);
+        assert_eq!(bus.readw(0xFFFD), 0xBEEF);
+    }
+
+    #[test]
+    pub fn test_unusable_region() {
+        let mut bus = MemBus::from_bytes(&[]);
+
+        bus.writeb(0xFEA0, 0x42);
+        assert_eq!(bus.readb(0xFEA0), 0x00);
+    }
+
+    #[test]
+    pub fn test_oam_dma() {
+        let mut bus = MemBus::from_bytes(&[]);
+        for i in 0..0xA0u16 {
+            bus.writeb(0xC100 + i, i as u8 ^ 0x5A);
+        }
+        bus.writeb(0xFF80, 0x42);
+
+        bus.writeb(0xFF46, 0xC1);
+        tick_n(&mut bus, 2);
+        // only HRAM is reachable while the transfer runs
+        assert_eq!(bus.readb(0xFF80), 0x42);
+        assert_eq!(bus.readb(0xC100), 0x5A);
+        assert_eq!(bus.readb(0x0000), 0x5A);
+        bus.writeb(0xC000, 0x12);
+
+        tick_n(&mut bus, 159);
+        assert_eq!(bus.readb(0xC000), 0x00);
+        for i in 0..0xA0u16 {
+            assert_eq!(bus.readb(0xFE00 + i), i as u8 ^ 0x5A);
         }
+        assert_eq!(bus.readb(0xFF46), 0xC1);
     }
 
-    pub fn writeb(&self, _addr: u16, _byte: u8) {
-        //todo
+    #[test]
+    pub fn test_oam_dma_duration() {
+        let mut bus = MemBus::from_bytes(&[]);
+        bus.writeb(0xC09E, 0x77);
+        bus.writeb(0xFF46, 0xC0);
+
+        // one cycle of setup, then one byte per cycle
+        tick_n(&mut bus, 160);
+        assert_eq!(bus.readb(0xFF46), 0x77);
+        tick_n(&mut bus, 1);
+        assert_eq!(bus.readb(0xFF46), 0xC0);
+        assert_eq!(bus.readb(0xFE9E), 0x77);
     }
 
-    pub fn writew(&self, _addr: u16, _word: u16) {
-        //todo
+    #[test]
+    pub fn test_oam_dma_restart() {
+        let mut bus = MemBus::from_bytes(&[]);
+        for i in 0..0xA0u16 {
+            bus.writeb(0xC100 + i, i as u8 ^ 0x5A);
+            bus.writeb(0xC200 + i, i as u8 ^ 0xA5);
+        }
+
+        bus.writeb(0xFF46, 0xC1);
+        tick_n(&mut bus, 12);
+        bus.writeb(0xFF46, 0xC2);
+        // the first transfer goes on while the second one starts
+        tick_n(&mut bus, 1);
+        assert_eq!(bus.readb(0xC000), 11 ^ 0x5A);
+
+        tick_n(&mut bus, 160);
+        for i in 0..0xA0u16 {
+            assert_eq!(bus.readb(0xFE00 + i), i as u8 ^ 0xA5);
+        }
     }
 }
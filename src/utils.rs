@@ -1,6 +1,16 @@
-use std::io::Read;
-
-use crate::{cpu::instructions::Instruction, mem_bus::MemBus};
+use std::{path::Path, rc::Rc};
+
+use crate::{
+    cpu::instructions::Instruction,
+    mem_bus::{
+        MemBus,
+        cartridge::{
+            Cartridge,
+            loader::{RomError, read_rom},
+        },
+        mbc::rtc::Clock,
+    },
+};
 
 #[inline(always)]
 pub fn panic_illegal_instr(instruction: Instruction) -> ! {
@@ -89,14 +99,10 @@ impl From<Value> for u16{
     }
 }
 
-pub fn open_rom(path: &str) -> Result<MemBus, std::io::Error>{
-    let mut file = std::fs::File::open(path)?;
-    let mut bytes: [u8; 0x10000] = core::array::from_fn(|_|0);
-    let read = file.read(&mut bytes)?;
-
-    println!(";; read : 0x{read:0X} bytes");
+pub fn open_rom(path: &str, clock: Rc<dyn Clock>) -> Result<MemBus, RomError>{
+    let bytes = read_rom(Path::new(path))?;
 
-    Ok(MemBus::from_bytes(&bytes))   
+    Ok(MemBus::from_cartridge(Cartridge::from_bytes(&bytes, clock)))
 }
 
 //MARK: TEST